@@ -83,10 +83,19 @@ static TO_DEST_FRAGMENT_SRC: &'static str = "
     }
 ";
 
-fn main() {
+fn build_display() -> simple_gl::Display {
     use simple_gl::DisplayBuild;
 
-    let display = glutin::WindowBuilder::new().build_simple_gl().unwrap();
+    #[cfg(feature = "headless")]
+    let builder = glutin::HeadlessRendererBuilder::new(1024, 768);
+    #[cfg(not(feature = "headless"))]
+    let builder = glutin::WindowBuilder::new();
+
+    builder.build_simple_gl().unwrap()
+}
+
+fn main() {
+    let display = build_display();
 
     let to_texture_program = simple_gl::Program::new(&display, TO_TEXTURE_VERTEX_SRC, TO_TEXTURE_FRAGMENT_SRC, None).unwrap();
     let to_dest_program = simple_gl::Program::new(&display, TO_DEST_VERTEX_SRC, TO_DEST_FRAGMENT_SRC, None).unwrap();
@@ -127,6 +136,12 @@ fn main() {
         to_dest_uniforms.set_texture("uTexture", &texture);
 
         display.draw().draw(&(&to_dest_vertex_buffer, &to_dest_index_buffer, &to_dest_uniforms));
+
+        // a headless display only draws a single frame
+        if cfg!(feature = "headless") {
+            break 'main;
+        }
+
         timer::sleep(Duration::milliseconds(17));
 
         for event in display.poll_events().move_iter() {
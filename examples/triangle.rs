@@ -6,13 +6,20 @@ extern crate simple_gl_macros;
 extern crate glutin;
 extern crate simple_gl;
 
-fn main() {
+fn build_display() -> simple_gl::Display {
     use simple_gl::DisplayBuild;
 
+    #[cfg(feature = "headless")]
+    let builder = glutin::HeadlessRendererBuilder::new(1024, 768);
+    #[cfg(not(feature = "headless"))]
+    let builder = glutin::WindowBuilder::new();
+
+    builder.build_simple_gl().unwrap()
+}
+
+fn main() {
     // building the display, ie. the main object
-    let display = glutin::WindowBuilder::new()
-        .build_simple_gl()
-        .unwrap();
+    let display = build_display();
 
     // building the vertex buffer, which contains all the vertices that we will draw
     let vertex_buffer = {
@@ -90,6 +97,11 @@ fn main() {
         target.draw(&(&vertex_buffer, &index_buffer, &program));
         target.finish();
 
+        // a headless display only draws a single frame
+        if cfg!(feature = "headless") {
+            break 'main;
+        }
+
         // sleeping for some time in order not to use up too much CPU
         timer::sleep(Duration::milliseconds(17));
 
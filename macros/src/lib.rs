@@ -4,6 +4,7 @@
 extern crate rustc;
 extern crate syntax;
 
+mod uniform_block;
 mod vertex;
 
 #[doc(hidden)]
@@ -12,4 +13,6 @@ pub fn registrar(registry: &mut rustc::plugin::Registry) {
     use syntax::parse::token;
     registry.register_syntax_extension(token::intern("vertex_format"),
         syntax::ext::base::Decorator(box vertex::expand));
+    registry.register_syntax_extension(token::intern("uniform_block"),
+        syntax::ext::base::Decorator(box uniform_block::expand));
 }
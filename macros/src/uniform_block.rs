@@ -0,0 +1,122 @@
+use syntax::ast;
+use syntax::ext::base;
+use syntax::ext::build::AstBuilder;
+use syntax::ext::deriving::generic;
+use syntax::codemap;
+use syntax::attr::AttrMetaMethods;
+use syntax::parse::token;
+use syntax::ptr::P;
+
+/// Expand #[uniform_block]
+pub fn expand(ecx: &mut base::ExtCtxt, span: codemap::Span,
+              meta_item: &ast::MetaItem, item: &ast::Item,
+              push: |P<ast::Item>|)
+{
+    generic::TraitDef {
+        span: span,
+        attributes: Vec::new(),
+        path: generic::ty::Path {
+            path: vec!["simple_gl", "UniformBlock"],
+            lifetime: None,
+            params: Vec::new(),
+            global: true,
+        },
+        additional_bounds: Vec::new(),
+        generics: generic::ty::LifetimeBounds::empty(),
+        methods: vec![
+            generic::MethodDef {
+                name: "build_layout",
+                generics: generic::ty::LifetimeBounds::empty(),
+                explicit_self: None,
+                args: vec![
+                    generic::ty::Literal(generic::ty::Path {
+                        path: vec!["Option"],
+                        lifetime: None,
+                        params: vec![box generic::ty::Self],
+                        global: false,
+                    })
+                ],
+                ret_ty: generic::ty::Literal(
+                    generic::ty::Path::new(
+                        vec!["simple_gl", "UniformBlockLayout"]
+                    ),
+                ),
+                attributes: Vec::new(),
+                combine_substructure: generic::combine_substructure(body),
+            },
+        ],
+    }.expand(ecx, meta_item, item, push);
+}
+
+fn body(ecx: &mut base::ExtCtxt, span: codemap::Span,
+        substr: &generic::Substructure) -> P<ast::Expr>
+{
+    let ecx: &base::ExtCtxt = ecx;
+
+    match substr.fields {
+        &generic::StaticStruct(ref definition, generic::Named(ref fields)) => {
+            let type_ident = substr.type_ident;
+
+            let content = definition.fields.iter().zip(fields.iter())
+                .map(|(def, &(ident, _))| {
+                    let ref elem_type = def.node.ty;
+                    let ident_str = token::get_ident(ident);
+                    let ident_str = ident_str.get();
+
+                    // `#[array]` fields are GLSL arrays of their element type, as `[[f32, ..3], ..2]`
+                    //  would otherwise be a `mat2x3`
+                    let array_elem_type = if def.node.attrs.iter().any(|a| a.check_name("array")) {
+                        match elem_type.node {
+                            ast::TyFixedLengthVec(ref inner, _) => Some(inner.clone()),
+                            _ => {
+                                ecx.span_err(def.span, "`#[array]` can only be used on fixed-size \
+                                                        arrays");
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    let (gl_type, array_size) = match array_elem_type {
+                        Some(inner) => (
+                            quote_expr!(ecx, UniformValue::get_gl_type(None::<$inner>)),
+                            quote_expr!(ecx, Some(mem::size_of::<$elem_type>() / mem::size_of::<$inner>()))
+                        ),
+                        None => (
+                            quote_expr!(ecx, UniformValue::get_gl_type(None::<$elem_type>)),
+                            quote_expr!(ecx, None::<uint>)
+                        ),
+                    };
+
+                    // the offset is computed from the addresses of the fields, so that the
+                    // padding inserted by the compiler is taken into account
+                    quote_expr!(ecx, {
+                        layout.push((
+                            $ident_str.to_string(),
+                            $gl_type,
+                            $array_size,
+                            (&dummy.$ident as *const _ as uint) - (&dummy as *const _ as uint)
+                        ));
+                    })
+
+                }).collect::<Vec<P<ast::Expr>>>();
+
+            quote_expr!(ecx, {
+                use simple_gl::UniformValue;
+                use std::mem;
+
+                let dummy: $type_ident = unsafe { mem::uninitialized() };
+                let mut layout = Vec::new();
+                $content;
+                layout
+            })
+        },
+
+        _ => {
+            ecx.span_err(span, "Unable to implement `simple_gl::UniformBlock::build_layout` \
+                                on a non-structure");
+            ecx.expr_lit(span, ast::LitNil)
+        }
+    }
+}
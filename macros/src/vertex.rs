@@ -5,6 +5,7 @@ use syntax::ext::base;
 use syntax::ext::build::AstBuilder;
 use syntax::ext::deriving::generic;
 use syntax::{attr, codemap};
+use syntax::attr::AttrMetaMethods;
 use syntax::parse::token;
 use syntax::ptr::P;
 
@@ -68,11 +69,17 @@ fn body(ecx: &mut base::ExtCtxt, span: codemap::Span,
                     let ident_str = token::get_ident(ident);
                     let ident_str = ident_str.get();
 
+                    // `#[normalized]` fields are converted to floats between 0.0 and 1.0
+                    //  (or -1.0 and 1.0 for signed types) by the GPU
+                    let normalized = def.node.attrs.iter()
+                        .any(|a| a.check_name("normalized"));
+
                     quote_expr!(ecx, {
                         bindings.insert($ident_str.to_string(), (
                             GLDataTuple::get_gl_type(None::<$elem_type>),
                             GLDataTuple::get_num_elems(None::<$elem_type>),
-                            offset_sum
+                            offset_sum,
+                            $normalized
                         ));
 
                         offset_sum += mem::size_of::<$elem_type>();
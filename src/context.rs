@@ -1,28 +1,42 @@
 use gl;
 use glutin;
 use native::NativeTaskBuilder;
-use std::sync::{Mutex, Future};
+use std::sync::{Arc, Mutex, Future};
 use std::task::TaskBuilder;
 use time;
 
+/// Content of the default framebuffer, row by row, from top to bottom.
+pub type Frame = Vec<Vec<(u8, u8, u8, u8)>>;
+
 enum Message {
     EndFrame,
     Execute(proc(&gl::Gl):Send),
+    Screenshot(Sender<Frame>),
 }
 
 pub struct Context {
     commands: Mutex<Sender<Message>>,
     events: Mutex<Receiver<glutin::Event>>,
+
+    /// Dimensions of the default framebuffer, updated at the start of each frame.
+    dimensions: Arc<Mutex<(uint, uint)>>,
+
+    /// Framebuffer object used instead of the default framebuffer, or `0`.
+    default_framebuffer: gl::types::GLuint,
 }
 
 impl Context {
-    pub fn new(window: glutin::Window) -> Context {
+    pub fn new_from_window(window: glutin::Window) -> Context {
         let (tx_events, rx_events) = channel();
         let (tx_commands, rx_commands) = channel();
 
+        let dimensions = Arc::new(Mutex::new(window.get_inner_size().unwrap_or((0, 0))));
+
         let context = Context {
             commands: Mutex::new(tx_commands),
             events: Mutex::new(rx_events),
+            dimensions: dimensions.clone(),
+            default_framebuffer: 0,
         };
 
         TaskBuilder::new().native().spawn(proc() {
@@ -30,6 +44,9 @@ impl Context {
 
             let gl = gl::Gl::load_with(|symbol| window.get_proc_address(symbol));
 
+            // content of the last frame that has been finished
+            let mut last_frame: Frame = Vec::new();
+
             let mut next_loop = time::precise_time_ns();
             'main: loop {
                 // sleeping until next frame must be drawn
@@ -45,25 +62,29 @@ impl Context {
                     }
                 });
 
-                // calling glViewport
-                {
-                    match window.get_inner_size() {
-                        Some(dimensions) => 
-                            gl.Viewport(0, 0, *dimensions.ref0() as gl::types::GLsizei,
-                                *dimensions.ref1() as gl::types::GLsizei),
-                        None => ()
-                    };
-                }
+                // updating the dimensions of the framebuffer, which are used as the default
+                // viewport of the targets
+                match window.get_inner_size() {
+                    Some(new_dimensions) => *dimensions.lock() = new_dimensions,
+                    None => ()
+                };
 
                 // processing commands
                 loop {
                     match rx_commands.recv_opt() {
                         Ok(EndFrame) => break,
                         Ok(Execute(cmd)) => cmd(&gl),
+                        Ok(Screenshot(sender)) => { let _ = sender.send_opt(last_frame.clone()); },
                         Err(_) => break 'main
                     }
                 }
 
+                // reading the back buffer before it gets swapped
+                {
+                    let (width, height) = window.get_inner_size().unwrap_or((0, 0));
+                    last_frame = read_frame(&gl, 0, Some(gl::BACK), width, height);
+                }
+
                 // swapping
                 window.swap_buffers();
 
@@ -82,6 +103,71 @@ impl Context {
         context
     }
 
+    /// Headless contexts don't have a depth or a stencil buffer, so if one is requested, the
+    /// commands draw on a framebuffer object that has them instead of the default framebuffer.
+    ///
+    /// Returns an error if this number of depth or stencil bits is not supported.
+    #[cfg(feature = "headless")]
+    pub fn new_from_headless(window: glutin::HeadlessContext, depth_bits: u8, stencil_bits: u8)
+        -> Result<Context, super::DisplayCreationError>
+    {
+        let (_, rx_events) = channel();
+        let (tx_commands, rx_commands) = channel();
+        let (tx_dimensions, rx_dimensions) = channel();
+
+        TaskBuilder::new().native().spawn(proc() {
+            unsafe { window.make_current(); }
+
+            let gl = gl::Gl::load_with(|symbol| window.get_proc_address(symbol));
+
+            // the initial viewport has the dimensions of the offscreen buffer
+            let (width, height) = unsafe {
+                let mut viewport = [0 as gl::types::GLint, ..4];
+                gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+                (viewport[2] as uint, viewport[3] as uint)
+            };
+
+            let framebuffer = build_headless_framebuffer(&gl, width, height, depth_bits,
+                stencil_bits);
+            tx_dimensions.send((width, height, framebuffer));
+
+            let framebuffer = match framebuffer {
+                Some(framebuffer) => framebuffer,
+                None => return
+            };
+
+            // content of the last frame that has been finished
+            let mut last_frame: Frame = Vec::new();
+
+            // there is no window to swap and no event to poll, so we simply execute
+            // the commands as they come
+            loop {
+                match rx_commands.recv_opt() {
+                    Ok(EndFrame) => {
+                        // the default read buffer is the one we are drawing to
+                        last_frame = read_frame(&gl, framebuffer, None, width, height);
+                    },
+                    Ok(Execute(cmd)) => cmd(&gl),
+                    Ok(Screenshot(sender)) => { let _ = sender.send_opt(last_frame.clone()); },
+                    Err(_) => break
+                }
+            }
+        });
+
+        let (width, height, framebuffer) = rx_dimensions.recv();
+        let framebuffer = match framebuffer {
+            Some(framebuffer) => framebuffer,
+            None => return Err(super::DepthStencilNotSupported(depth_bits, stencil_bits))
+        };
+
+        Ok(Context {
+            commands: Mutex::new(tx_commands),
+            events: Mutex::new(rx_events),
+            dimensions: Arc::new(Mutex::new((width, height))),
+            default_framebuffer: framebuffer,
+        })
+    }
+
     pub fn exec<T:Send>(&self, f: proc(&gl::Gl): Send -> T) -> Future<T> {
         let (tx, rx) = channel();
         self.commands.lock().send(Execute(proc(gl) {
@@ -90,6 +176,24 @@ impl Context {
         Future::from_receiver(rx)
     }
 
+    /// Returns the content of the last frame that has been finished.
+    pub fn screenshot(&self) -> Frame {
+        let (tx, rx) = channel();
+        self.commands.lock().send(Screenshot(tx));
+        rx.recv()
+    }
+
+    /// Returns the dimensions of the default framebuffer.
+    pub fn get_framebuffer_dimensions(&self) -> (uint, uint) {
+        self.dimensions.lock().clone()
+    }
+
+    /// Returns the framebuffer object that must be bound instead of the default framebuffer,
+    /// or `0` to use the default framebuffer.
+    pub fn get_default_framebuffer(&self) -> gl::types::GLuint {
+        self.default_framebuffer
+    }
+
     pub fn swap_buffers(&self) {
         self.commands.lock().send(EndFrame);
     }
@@ -107,3 +211,108 @@ impl Context {
         result
     }
 }
+
+/// Reads the content of a buffer of the default framebuffer, or of the framebuffer object that
+/// replaces it.
+///
+/// If `buffer` is `None`, the current read buffer is used.
+fn read_frame(gl: &gl::Gl, framebuffer: gl::types::GLuint, buffer: Option<gl::types::GLenum>,
+              width: uint, height: uint) -> Frame
+{
+    use libc;
+
+    let mut data = Vec::from_elem(width * height, (0u8, 0u8, 0u8, 0u8));
+
+    unsafe {
+        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        match buffer {
+            Some(buffer) => select_read_buffer(gl, buffer),
+            None => ()
+        };
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl.ReadPixels(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei,
+            gl::RGBA, gl::UNSIGNED_BYTE, data.as_mut_ptr() as *mut libc::c_void);
+    }
+
+    // OpenGL returns the rows from bottom to top
+    if width == 0 {
+        return Vec::new();
+    }
+    data.as_slice().chunks(width).rev().map(|row| row.to_vec()).collect()
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn select_read_buffer(gl: &gl::Gl, buffer: gl::types::GLenum) {
+    gl.ReadBuffer(buffer);
+}
+
+#[cfg(target_os = "android")]
+fn select_read_buffer(_: &gl::Gl, _: gl::types::GLenum) {
+    // GLES always reads from the back buffer
+}
+
+/// Builds the framebuffer object that replaces the default framebuffer of a headless context,
+/// with a color buffer and the requested depth and stencil buffers.
+///
+/// Returns `Some(0)` if neither is requested, and `None` if this number of bits is not
+/// supported.
+#[cfg(feature = "headless", target_os = "windows")]
+#[cfg(feature = "headless", target_os = "linux")]
+#[cfg(feature = "headless", target_os = "macos")]
+fn build_headless_framebuffer(gl: &gl::Gl, width: uint, height: uint, depth_bits: u8,
+                              stencil_bits: u8) -> Option<gl::types::GLuint>
+{
+    use std::mem;
+
+    let (format, attachment) = if depth_bits == 0 && stencil_bits == 0 {
+        return Some(0);
+    } else if depth_bits > 32 || stencil_bits > 8 {
+        return None;
+    } else if stencil_bits == 0 {
+        (if depth_bits <= 24 { gl::DEPTH_COMPONENT24 } else { gl::DEPTH_COMPONENT32F },
+            gl::DEPTH_ATTACHMENT)
+    } else if depth_bits == 0 {
+        (gl::STENCIL_INDEX8, gl::STENCIL_ATTACHMENT)
+    } else {
+        (if depth_bits <= 24 { gl::DEPTH24_STENCIL8 } else { gl::DEPTH32F_STENCIL8 },
+            gl::DEPTH_STENCIL_ATTACHMENT)
+    };
+
+    let width = width as gl::types::GLsizei;
+    let height = height as gl::types::GLsizei;
+
+    unsafe {
+        let mut render_buffers: [gl::types::GLuint, ..2] = mem::uninitialized();
+        gl.GenRenderbuffers(2, render_buffers.as_mut_ptr());
+        gl.BindRenderbuffer(gl::RENDERBUFFER, render_buffers[0]);
+        gl.RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width, height);
+        gl.BindRenderbuffer(gl::RENDERBUFFER, render_buffers[1]);
+        gl.RenderbufferStorage(gl::RENDERBUFFER, format, width, height);
+
+        let mut framebuffer: gl::types::GLuint = mem::uninitialized();
+        gl.GenFramebuffers(1, &mut framebuffer);
+        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER,
+            render_buffers[0]);
+        gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER,
+            render_buffers[1]);
+
+        if gl.CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl.DeleteFramebuffers(1, &framebuffer);
+            gl.DeleteRenderbuffers(2, render_buffers.as_ptr());
+            return None;
+        }
+
+        Some(framebuffer)
+    }
+}
+
+#[cfg(feature = "headless", target_os = "android")]
+fn build_headless_framebuffer(_: &gl::Gl, _: uint, _: uint, depth_bits: u8, stencil_bits: u8)
+    -> Option<gl::types::GLuint>
+{
+    if depth_bits == 0 && stencil_bits == 0 { Some(0) } else { None }
+}
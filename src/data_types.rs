@@ -60,11 +60,37 @@ impl GLDataTuple for u8 {
     fn get_num_elems(_: Option<(u8)>) -> gl::types::GLint { 1 }
 }
 
+impl GLDataTuple for i16 {
+    fn get_gl_type(_: Option<(i16)>) -> gl::types::GLenum { gl::SHORT }
+    fn get_num_elems(_: Option<(i16)>) -> gl::types::GLint { 1 }
+}
+
+impl GLDataTuple for u16 {
+    fn get_gl_type(_: Option<(u16)>) -> gl::types::GLenum { gl::UNSIGNED_SHORT }
+    fn get_num_elems(_: Option<(u16)>) -> gl::types::GLint { 1 }
+}
+
+impl GLDataTuple for i32 {
+    fn get_gl_type(_: Option<(i32)>) -> gl::types::GLenum { gl::INT }
+    fn get_num_elems(_: Option<(i32)>) -> gl::types::GLint { 1 }
+}
+
+impl GLDataTuple for u32 {
+    fn get_gl_type(_: Option<(u32)>) -> gl::types::GLenum { gl::UNSIGNED_INT }
+    fn get_num_elems(_: Option<(u32)>) -> gl::types::GLint { 1 }
+}
+
 impl GLDataTuple for f32 {
     fn get_gl_type(_: Option<(f32)>) -> gl::types::GLenum { gl::FLOAT }
     fn get_num_elems(_: Option<(f32)>) -> gl::types::GLint { 1 }
 }
 
+#[cfg(not(target_os = "android"))]
+impl GLDataTuple for f64 {
+    fn get_gl_type(_: Option<(f64)>) -> gl::types::GLenum { gl::DOUBLE }
+    fn get_num_elems(_: Option<(f64)>) -> gl::types::GLint { 1 }
+}
+
 impl<T: GLDataTuple> GLDataTuple for (T, T) {
     fn get_gl_type(_: Option<(T, T)>) -> gl::types::GLenum { GLDataTuple::get_gl_type(None::<T>) }
     fn get_num_elems(_: Option<(T, T)>) -> gl::types::GLint { GLDataTuple::get_num_elems(None::<T>) * 2 }
@@ -100,27 +126,22 @@ pub trait UniformValue {
     fn get_gl_type(Option<Self>) -> gl::types::GLenum;
 }
 
-impl UniformValue for i8 {
-    fn get_gl_type(_: Option<i8>) -> gl::types::GLenum {
-        gl::BYTE
-    }
-}
-
-impl UniformValue for u8 {
-    fn get_gl_type(_: Option<u8>) -> gl::types::GLenum {
-        gl::UNSIGNED_BYTE
+impl UniformValue for i32 {
+    fn get_gl_type(_: Option<i32>) -> gl::types::GLenum {
+        gl::INT
     }
 }
 
-impl UniformValue for i16 {
-    fn get_gl_type(_: Option<i16>) -> gl::types::GLenum {
-        gl::SHORT
+#[cfg(not(target_os = "android"))]
+impl UniformValue for u32 {
+    fn get_gl_type(_: Option<u32>) -> gl::types::GLenum {
+        gl::UNSIGNED_INT
     }
 }
 
-impl UniformValue for u16 {
-    fn get_gl_type(_: Option<u16>) -> gl::types::GLenum {
-        gl::UNSIGNED_SHORT
+impl UniformValue for bool {
+    fn get_gl_type(_: Option<bool>) -> gl::types::GLenum {
+        gl::BOOL
     }
 }
 
@@ -130,10 +151,78 @@ impl UniformValue for f32 {
     }
 }
 
+impl UniformValue for [f32, ..2] {
+    fn get_gl_type(_: Option<[f32, ..2]>) -> gl::types::GLenum {
+        gl::FLOAT_VEC2
+    }
+}
+
+impl UniformValue for [f32, ..3] {
+    fn get_gl_type(_: Option<[f32, ..3]>) -> gl::types::GLenum {
+        gl::FLOAT_VEC3
+    }
+}
+
+impl UniformValue for [f32, ..4] {
+    fn get_gl_type(_: Option<[f32, ..4]>) -> gl::types::GLenum {
+        gl::FLOAT_VEC4
+    }
+}
+
+impl UniformValue for [i32, ..2] {
+    fn get_gl_type(_: Option<[i32, ..2]>) -> gl::types::GLenum {
+        gl::INT_VEC2
+    }
+}
+
+impl UniformValue for [i32, ..3] {
+    fn get_gl_type(_: Option<[i32, ..3]>) -> gl::types::GLenum {
+        gl::INT_VEC3
+    }
+}
+
+impl UniformValue for [i32, ..4] {
+    fn get_gl_type(_: Option<[i32, ..4]>) -> gl::types::GLenum {
+        gl::INT_VEC4
+    }
+}
+
 #[cfg(not(target_os = "android"))]
-impl UniformValue for f64 {
-    fn get_gl_type(_: Option<f64>) -> gl::types::GLenum {
-        gl::DOUBLE
+impl UniformValue for [u32, ..2] {
+    fn get_gl_type(_: Option<[u32, ..2]>) -> gl::types::GLenum {
+        gl::UNSIGNED_INT_VEC2
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [u32, ..3] {
+    fn get_gl_type(_: Option<[u32, ..3]>) -> gl::types::GLenum {
+        gl::UNSIGNED_INT_VEC3
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [u32, ..4] {
+    fn get_gl_type(_: Option<[u32, ..4]>) -> gl::types::GLenum {
+        gl::UNSIGNED_INT_VEC4
+    }
+}
+
+impl UniformValue for [bool, ..2] {
+    fn get_gl_type(_: Option<[bool, ..2]>) -> gl::types::GLenum {
+        gl::BOOL_VEC2
+    }
+}
+
+impl UniformValue for [bool, ..3] {
+    fn get_gl_type(_: Option<[bool, ..3]>) -> gl::types::GLenum {
+        gl::BOOL_VEC3
+    }
+}
+
+impl UniformValue for [bool, ..4] {
+    fn get_gl_type(_: Option<[bool, ..4]>) -> gl::types::GLenum {
+        gl::BOOL_VEC4
     }
 }
 
@@ -154,3 +243,51 @@ impl UniformValue for [[f32, ..4], ..4] {
         gl::FLOAT_MAT4
     }
 }
+
+// matrix with 2 columns and 3 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..3], ..2] {
+    fn get_gl_type(_: Option<[[f32, ..3], ..2]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT2x3
+    }
+}
+
+// matrix with 2 columns and 4 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..4], ..2] {
+    fn get_gl_type(_: Option<[[f32, ..4], ..2]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT2x4
+    }
+}
+
+// matrix with 3 columns and 2 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..2], ..3] {
+    fn get_gl_type(_: Option<[[f32, ..2], ..3]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT3x2
+    }
+}
+
+// matrix with 3 columns and 4 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..4], ..3] {
+    fn get_gl_type(_: Option<[[f32, ..4], ..3]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT3x4
+    }
+}
+
+// matrix with 4 columns and 2 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..2], ..4] {
+    fn get_gl_type(_: Option<[[f32, ..2], ..4]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT4x2
+    }
+}
+
+// matrix with 4 columns and 3 rows.
+#[cfg(not(target_os = "android"))]
+impl UniformValue for [[f32, ..3], ..4] {
+    fn get_gl_type(_: Option<[[f32, ..3], ..4]>) -> gl::types::GLenum {
+        gl::FLOAT_MAT4x3
+    }
+}
@@ -0,0 +1,498 @@
+use gl;
+use render_buffer;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use texture;
+use texture::{Texture, TextureImpl};
+
+/// Builds a target that draws on multiple textures at once.
+///
+/// Each color texture is attached to a different color attachment of the framebuffer. By
+/// default the first texture receives the fragment output at location 0, the second one the
+/// output at location 1, and so on. You can instead give a name to a texture, in which case it
+/// receives the output of the fragment shader with this name.
+///
+/// The textures are only borrowed immutably, so a texture can be sampled while another one is
+/// drawn on.
+///
+/// # Example
+///
+/// ```no_run
+/// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+/// # let albedo: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+/// # let normal: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+/// # let depth: simple_gl::RenderBuffer = unsafe { std::mem::uninitialized() };
+/// let mut target = simple_gl::FramebufferBuilder::new(&display)
+///     .with_named_color_texture("oAlbedo", &albedo)
+///     .with_named_color_texture("oNormal", &normal)
+///     .with_depth_stencil_attachment(simple_gl::RenderBufferAttachment(&depth))
+///     .draw().unwrap();
+/// target.clear_depth(1.0);
+/// ```
+pub struct FramebufferBuilder<'t> {
+    display: Arc<super::DisplayImpl>,
+    colors: Vec<(Option<String>, &'t Texture)>,
+    depth_stencil: Vec<super::DepthStencilAttachment<'t>>,
+}
+
+impl<'t> FramebufferBuilder<'t> {
+    /// Starts building a framebuffer without any attachment.
+    pub fn new(display: &super::Display) -> FramebufferBuilder<'t> {
+        FramebufferBuilder {
+            display: display.context.clone(),
+            colors: Vec::new(),
+            depth_stencil: Vec::new(),
+        }
+    }
+
+    /// Adds a color texture, which receives the next unnamed fragment output.
+    pub fn with_color_texture(mut self, texture: &'t Texture) -> FramebufferBuilder<'t> {
+        self.colors.push((None, texture));
+        self
+    }
+
+    /// Adds a color texture, which receives the fragment output with the given name.
+    ///
+    /// Named outputs are not supported by GLES.
+    pub fn with_named_color_texture(mut self, name: &str, texture: &'t Texture)
+        -> FramebufferBuilder<'t>
+    {
+        self.colors.push((Some(name.to_string()), texture));
+        self
+    }
+
+    /// Adds a depth and/or stencil buffer.
+    pub fn with_depth_stencil_attachment(mut self, attachment: super::DepthStencilAttachment<'t>)
+        -> FramebufferBuilder<'t>
+    {
+        self.depth_stencil.push(attachment);
+        self
+    }
+
+    /// Start drawing on the attachments.
+    ///
+    /// There must be at least one color texture, none of the textures can be a cubemap, and
+    /// all the attachments must have the same dimensions. Returns an error otherwise, or if the
+    /// combination of attachments is not supported by the implementation.
+    ///
+    /// The framebuffer is destroyed when the target is dropped. Use `build` instead if you
+    /// draw on the same attachments repeatedly.
+    pub fn draw(self) -> Result<super::Target<'t>, super::FramebufferCreationError> {
+        let (display, textures, fbo) = try!(self.build_impl());
+
+        Ok(super::Target {
+            display: display,
+            display_hold: None,
+            textures: textures,
+            dimensions: fbo.dimensions,
+            viewport: None,
+            scissor: None,
+            framebuffer: Some(Arc::new(fbo)),
+            execute_end: None,
+        })
+    }
+
+    /// Builds a `Framebuffer` that can be drawn on multiple times.
+    ///
+    /// Returns the same errors as `draw`.
+    pub fn build(self) -> Result<Framebuffer<'t>, super::FramebufferCreationError> {
+        let (display, textures, fbo) = try!(self.build_impl());
+
+        Ok(Framebuffer {
+            display: display,
+            textures: textures,
+            framebuffer: Arc::new(fbo),
+        })
+    }
+
+    /// Builds the framebuffer object and checks that it is complete.
+    fn build_impl(self)
+        -> Result<(Arc<super::DisplayImpl>, Vec<&'t Texture>, FrameBufferObject),
+                  super::FramebufferCreationError>
+    {
+        let FramebufferBuilder { display, colors, depth_stencil } = self;
+
+        let fbo = {
+            let colors: Vec<(Option<String>, &TextureImpl, Option<gl::types::GLenum>)> =
+                colors.iter().map(|&(ref name, texture)| {
+                    (name.clone(), &**texture::get_impl(texture), None)
+                }).collect();
+
+            try!(FrameBufferObject::new(display.clone(), colors.as_slice(),
+                depth_stencil.as_slice()))
+        };
+
+        try!(get_status_result(fbo.status));
+
+        Ok((display, colors.move_iter().map(|(_, texture)| texture).collect(), fbo))
+    }
+}
+
+/// A framebuffer whose attachments are bound once and that can be drawn on repeatedly.
+///
+/// Built with `FramebufferBuilder::build`. The framebuffer immutably borrows its attachments for
+/// as long as it exists.
+///
+/// # Example
+///
+/// ```no_run
+/// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+/// # let texture: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+/// let mut framebuffer = simple_gl::FramebufferBuilder::new(&display)
+///     .with_color_texture(&texture)
+///     .build().unwrap();
+///
+/// loop {
+///     let mut target = framebuffer.draw();
+///     // ...
+/// #   break;
+/// }
+/// ```
+pub struct Framebuffer<'t> {
+    display: Arc<super::DisplayImpl>,
+    textures: Vec<&'t Texture>,
+    framebuffer: Arc<FrameBufferObject>,
+}
+
+impl<'t> Framebuffer<'t> {
+    /// Start drawing on the framebuffer.
+    pub fn draw<'a>(&'a mut self) -> super::Target<'a> {
+        super::Target {
+            display: self.display.clone(),
+            display_hold: None,
+            textures: self.textures.iter().map(|t| *t).collect(),
+            dimensions: self.framebuffer.dimensions,
+            viewport: None,
+            scissor: None,
+            framebuffer: Some(self.framebuffer.clone()),
+            execute_end: None,
+        }
+    }
+}
+
+impl<'t> fmt::Show for Framebuffer<'t> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        (format!("Framebuffer #{} ({} color attachments)", self.framebuffer.id,
+            self.textures.len())).fmt(formatter)
+    }
+}
+
+/// Frame buffer.
+pub struct FrameBufferObject {
+    display: Arc<super::DisplayImpl>,
+    pub id: gl::types::GLuint,
+
+    /// The name of the fragment output written to each color attachment, or `None` if
+    /// the attachment receives the output at the same location as the attachment.
+    pub outputs: Vec<Option<String>>,
+
+    /// The value returned by `glCheckFramebufferStatus` after the attachments were bound.
+    pub status: gl::types::GLenum,
+
+    /// The width and height of the attachments.
+    pub dimensions: (uint, uint),
+}
+
+impl FrameBufferObject {
+    /// Builds a new FBO and attaches color textures and depth/stencil buffers to it.
+    ///
+    /// Each color texture comes with the name of its fragment output, and with the face to
+    /// attach if the texture is a cubemap.
+    ///
+    /// Returns an error if the attachments are invalid. The completeness of the framebuffer is
+    /// not checked, its status is stored in the returned object instead.
+    pub fn new(display: Arc<super::DisplayImpl>,
+               colors: &[(Option<String>, &TextureImpl, Option<gl::types::GLenum>)],
+               depth_stencil: &[super::DepthStencilAttachment])
+        -> Result<FrameBufferObject, super::FramebufferCreationError>
+    {
+        let (width, height) = match colors.get(0) {
+            Some(&(_, texture, _)) => (texture.width, texture.height),
+            None => return Err(super::FramebufferNoColorAttachment)
+        };
+
+        check_outputs_support(colors.len(), colors.iter().any(|&(ref n, _, _)| n.is_some()));
+
+        // attachment point, bind point or face, and id of each color texture
+        let mut colors_ids = Vec::new();
+
+        for (index, &(_, texture, face)) in colors.iter().enumerate() {
+            if texture.width != width || texture.height != height {
+                return Err(super::FramebufferDimensionsMismatch);
+            }
+
+            if face.is_none() && texture.bind_point == gl::TEXTURE_CUBE_MAP {
+                return Err(super::FramebufferCubemapAttachment);
+            }
+
+            colors_ids.push((gl::COLOR_ATTACHMENT0 + index as gl::types::GLenum,
+                face.unwrap_or(texture.bind_point), face.is_some(), texture.id));
+        }
+
+        // attachment point, whether the attachment is a render buffer, and id
+        let mut attachments_ids = Vec::new();
+
+        for attachment in depth_stencil.iter() {
+            match attachment {
+                &super::RenderBufferAttachment(buffer) => {
+                    let buffer = render_buffer::get_impl(buffer);
+                    if buffer.width != width || buffer.height != height {
+                        return Err(super::FramebufferDimensionsMismatch);
+                    }
+
+                    attachments_ids.push((buffer.attachment, true, buffer.id));
+                },
+
+                &super::DepthTextureAttachment(texture) => {
+                    let texture = texture::get_impl(texture);
+                    if texture.width != width || texture.height != height {
+                        return Err(super::FramebufferDimensionsMismatch);
+                    }
+
+                    let attachment = match texture::get_depth_stencil_attachment(texture.internal_format) {
+                        Some(a) => a,
+                        None => return Err(super::FramebufferNotADepthTexture)
+                    };
+
+                    attachments_ids.push((attachment, false, texture.id));
+                },
+            }
+        }
+
+        let (id, status) = display.context.exec(proc(gl) {
+            unsafe {
+                let id: gl::types::GLuint = mem::uninitialized();
+                gl.GenFramebuffers(1, mem::transmute(&id));
+                gl.BindFramebuffer(gl::FRAMEBUFFER, id);
+
+                for &(attachment, target, is_face, texture_id) in colors_ids.iter() {
+                    if is_face {
+                        gl.FramebufferTexture2D(gl::FRAMEBUFFER, attachment, target, texture_id, 0);
+                    } else {
+                        texture::attach_texture(gl, attachment, target, texture_id);
+                    }
+                }
+
+                for &(attachment, is_render_buffer, attachment_id) in attachments_ids.iter() {
+                    if is_render_buffer {
+                        gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER,
+                            attachment_id);
+                    } else {
+                        texture::attach_texture(gl, attachment, gl::TEXTURE_2D, attachment_id);
+                    }
+                }
+
+                set_default_draw_buffers(gl, colors_ids.len());
+                (id, gl.CheckFramebufferStatus(gl::FRAMEBUFFER))
+            }
+        }).get();
+
+        Ok(FrameBufferObject {
+            display: display,
+            id: id,
+            outputs: colors.iter().map(|&(ref name, _, _)| name.clone()).collect(),
+            status: status,
+            dimensions: (width, height),
+        })
+    }
+}
+
+impl Drop for FrameBufferObject {
+    fn drop(&mut self) {
+        purge_framebuffer(&self.display, self.id);
+
+        let id = self.id.clone();
+        self.display.context.exec(proc(gl) {
+            unsafe { gl.DeleteFramebuffers(1, [ id ].as_ptr()); }
+        });
+    }
+}
+
+/// Turns the status of a framebuffer into a `Result`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_status_result(status: gl::types::GLenum) -> Result<(), super::FramebufferCreationError> {
+    match status {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err(super::FramebufferIncompleteAttachment),
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => Err(super::FramebufferIncompleteMultisample),
+        gl::FRAMEBUFFER_UNSUPPORTED => Err(super::FramebufferUnsupported),
+        status => Err(super::FramebufferIncomplete(status))
+    }
+}
+
+/// Turns the status of a framebuffer into a `Result`.
+#[cfg(target_os = "android")]
+fn get_status_result(status: gl::types::GLenum) -> Result<(), super::FramebufferCreationError> {
+    match status {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err(super::FramebufferIncompleteAttachment),
+        gl::FRAMEBUFFER_UNSUPPORTED => Err(super::FramebufferUnsupported),
+        status => Err(super::FramebufferIncomplete(status))
+    }
+}
+
+/// Returns the draw buffers that make each color attachment of the framebuffer receive the
+/// output of the program with the same name, or at the same location if it has no name.
+///
+/// Returns `None` if none of the attachments has a name, in which case the default draw
+/// buffers of the framebuffer are used. The draw buffers are computed once for each
+/// combination of program and framebuffer.
+///
+/// Fails if two color attachments receive the output at the same location.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn get_draw_buffers(display: &Arc<super::DisplayImpl>, program: gl::types::GLuint,
+                        framebuffer: &FrameBufferObject) -> Option<Vec<gl::types::GLenum>>
+{
+    if framebuffer.outputs.iter().all(|o| o.is_none()) {
+        return None;
+    }
+
+    let key = (program, framebuffer.id);
+
+    match display.draw_buffers.lock().find(&key) {
+        Some(draw_buffers) => return Some(draw_buffers.clone()),
+        None => ()
+    }
+
+    // location of the output received by each color attachment, or `None` if the output
+    //  is not used by the program
+    let outputs = framebuffer.outputs.clone();
+    let locations: Vec<Option<uint>> = display.context.exec(proc(gl) {
+        outputs.iter().enumerate().map(|(index, output)| {
+            match output {
+                &Some(ref name) => {
+                    let name = name.to_c_str();
+                    let location = unsafe { gl.GetFragDataLocation(program, name.as_ptr()) };
+                    if location < 0 { None } else { Some(location as uint) }
+                },
+                &None => Some(index)
+            }
+        }).collect()
+    }).get();
+
+    let mut draw_buffers = Vec::from_elem(locations.len(), gl::NONE);
+
+    for (index, location) in locations.iter().enumerate() {
+        let location = match location {
+            &Some(location) => location,
+            &None => continue
+        };
+
+        if location >= draw_buffers.len() {
+            let missing = location + 1 - draw_buffers.len();
+            draw_buffers.grow(missing, &gl::NONE);
+        }
+
+        let previous = *draw_buffers.get(location);
+        if previous != gl::NONE {
+            fail!("The color attachments {} and {} both receive the fragment output at \
+                   location {}", previous - gl::COLOR_ATTACHMENT0, index, location);
+        }
+
+        *draw_buffers.get_mut(location) = gl::COLOR_ATTACHMENT0 + index as gl::types::GLenum;
+    }
+
+    display.draw_buffers.lock().insert(key, draw_buffers.clone());
+    Some(draw_buffers)
+}
+
+/// Named fragment outputs are not supported by GLES.
+#[cfg(target_os = "android")]
+pub fn get_draw_buffers(_: &Arc<super::DisplayImpl>, _: gl::types::GLuint, _: &FrameBufferObject)
+    -> Option<Vec<gl::types::GLenum>>
+{
+    None
+}
+
+/// Binds the draw buffers returned by `get_draw_buffers` to the framebuffer currently bound.
+///
+/// Does nothing if `draw_buffers` is `None`. Otherwise `unbind_fragment_outputs` must be
+/// called after the draw.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn bind_fragment_outputs(gl: &gl::Gl, draw_buffers: &Option<Vec<gl::types::GLenum>>) {
+    match draw_buffers {
+        &Some(ref draw_buffers) => unsafe {
+            gl.DrawBuffers(draw_buffers.len() as gl::types::GLsizei, draw_buffers.as_ptr());
+        },
+        &None => ()
+    }
+}
+
+/// Named fragment outputs are not supported by GLES.
+#[cfg(target_os = "android")]
+pub fn bind_fragment_outputs(_: &gl::Gl, _: &Option<Vec<gl::types::GLenum>>) {
+}
+
+/// Restores the draw buffers modified by `bind_fragment_outputs`, so that clearing the
+/// framebuffer affects all of its `colors_count` color attachments.
+pub fn unbind_fragment_outputs(gl: &gl::Gl, draw_buffers: &Option<Vec<gl::types::GLenum>>,
+                               colors_count: uint)
+{
+    if draw_buffers.is_some() {
+        set_default_draw_buffers(gl, colors_count);
+    }
+}
+
+/// Forgets the draw buffers computed for a program. Must be called when the program
+/// is destroyed.
+pub fn purge_program(display: &Arc<super::DisplayImpl>, program: gl::types::GLuint) {
+    purge_draw_buffers(display, |&(p, _)| p == program);
+}
+
+/// Forgets the draw buffers computed for a framebuffer.
+fn purge_framebuffer(display: &Arc<super::DisplayImpl>, framebuffer: gl::types::GLuint) {
+    purge_draw_buffers(display, |&(_, f)| f == framebuffer);
+}
+
+fn purge_draw_buffers(display: &Arc<super::DisplayImpl>,
+                      filter: |&(gl::types::GLuint, gl::types::GLuint)| -> bool)
+{
+    let mut draw_buffers = display.draw_buffers.lock();
+
+    let keys: Vec<(gl::types::GLuint, gl::types::GLuint)> = draw_buffers.keys()
+        .filter(|k| filter(*k)).map(|k| k.clone()).collect();
+
+    for key in keys.iter() {
+        draw_buffers.pop(key);
+    }
+}
+
+/// Makes the framebuffer currently bound draw on its first `count` color attachments.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn set_default_draw_buffers(gl: &gl::Gl, count: uint) {
+    let draw_buffers: Vec<gl::types::GLenum> = range(0, count)
+        .map(|i| gl::COLOR_ATTACHMENT0 + i as gl::types::GLenum).collect();
+
+    unsafe { gl.DrawBuffers(count as gl::types::GLsizei, draw_buffers.as_ptr()); }
+}
+
+/// GLES always draws on the first color attachment.
+#[cfg(target_os = "android")]
+fn set_default_draw_buffers(_: &gl::Gl, _: uint) {
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn check_outputs_support(_: uint, _: bool) {
+}
+
+#[cfg(target_os = "android")]
+fn check_outputs_support(colors_count: uint, named: bool) {
+    if colors_count > 1 {
+        fail!("Multiple color attachments are not supported by GLES");
+    }
+
+    if named {
+        fail!("Named fragment outputs are not supported by GLES");
+    }
+}
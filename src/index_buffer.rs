@@ -4,6 +4,7 @@ use libc;
 use std::fmt;
 use std::mem;
 use std::sync::Arc;
+use vertex_array_object;
 use PrimitiveType;
 
 /// A list of indices loaded in the graphics card's memory.
@@ -15,11 +16,6 @@ pub struct IndexBuffer {
     primitives: gl::types::GLenum
 }
 
-/// This public function is accessible from within `simple_gl` but not for the user.
-pub fn get_clone(ib: &IndexBuffer) -> (gl::types::GLuint, uint, gl::types::GLenum, gl::types::GLenum) {
-    (ib.id.clone(), ib.elements_count.clone(), ib.data_type.clone(), ib.primitives.clone())
-}
-
 impl IndexBuffer {
     /// Builds a new index buffer.
     ///
@@ -54,6 +50,102 @@ impl IndexBuffer {
             primitives: prim.get_gl_enum()
         }
     }
+
+    /// Returns the number of indices in the buffer.
+    pub fn len(&self) -> uint {
+        self.elements_count
+    }
+
+    /// Returns a slice of the buffer that starts at the index of position `first` and
+    /// contains `count` indices.
+    ///
+    /// Use it to draw a single mesh when a large buffer contains the indices of multiple
+    /// meshes. Fails if the slice is out of the bounds of the buffer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let vertex_buffer: simple_gl::VertexBuffer<u8> = unsafe { std::mem::uninitialized() };
+    /// # let index_buffer: simple_gl::IndexBuffer = unsafe { std::mem::uninitialized() };
+    /// # let uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+    /// # let mut target: simple_gl::Target = unsafe { std::mem::uninitialized() };
+    /// // the second mesh uses the indices 36 to 71, which start at 0 for its first vertex,
+    /// //  which is the vertex 24 of the vertex buffer
+    /// let indices = index_buffer.slice(36, 36).with_base_vertex(24);
+    /// target.draw(&(&vertex_buffer, &indices, &uniforms));
+    /// ```
+    ///
+    pub fn slice<'a>(&'a self, first: uint, count: uint) -> IndexBufferSlice<'a> {
+        if first + count > self.elements_count {
+            fail!("The slice {}..{} is out of the bounds of an index buffer of {} elements",
+                first, first + count, self.elements_count);
+        }
+
+        IndexBufferSlice {
+            buffer: self,
+            first: first,
+            count: count,
+            base_vertex: 0,
+        }
+    }
+
+    /// Reads the content of the buffer.
+    ///
+    /// `T` must be the type of the indices that were passed to `new`.
+    /// This function waits for all the pending draws that use the buffer to be finished,
+    /// so it is slow and should only be used for debugging.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let index_buffer: simple_gl::IndexBuffer = unsafe { std::mem::uninitialized() };
+    /// let indices: Vec<u16> = index_buffer.read();
+    /// ```
+    ///
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn read<T: data_types::GLDataType + Send>(&self) -> Vec<T> {
+        if data_types::GLDataType::get_gl_type(None::<T>) != self.data_type {
+            fail!("The type of indices doesn't match the type of the index buffer");
+        }
+
+        let id = self.id;
+        let elements_count = self.elements_count;
+        let size = elements_count * mem::size_of::<T>();
+
+        self.display.context.exec(proc(gl) {
+            let mut data: Vec<T> = Vec::with_capacity(elements_count);
+
+            unsafe {
+                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, id);
+                gl.GetBufferSubData(gl::ELEMENT_ARRAY_BUFFER, 0, size as gl::types::GLsizeiptr,
+                    data.as_mut_ptr() as *mut libc::c_void);
+                data.set_len(elements_count);
+            }
+
+            data
+        }).get()
+    }
+
+    /// Reads the content of the buffer.
+    #[cfg(target_os = "android")]
+    pub fn read<T: data_types::GLDataType + Send>(&self) -> Vec<T> {
+        fail!("Reading buffers is not supported by GLES")
+    }
+}
+
+impl IndicesSource for IndexBuffer {
+    fn to_indices_source_helper(&self) -> IndicesSourceHelper {
+        IndicesSourceHelper {
+            index_buffer: Some(self.id),
+            primitives: self.primitives,
+            data_type: self.data_type,
+            first: 0,
+            count: Some(self.elements_count),
+            base_vertex: 0,
+        }
+    }
 }
 
 impl fmt::Show for IndexBuffer {
@@ -64,9 +156,93 @@ impl fmt::Show for IndexBuffer {
 
 impl Drop for IndexBuffer {
     fn drop(&mut self) {
+        vertex_array_object::purge_buffer(&self.display, self.id);
+
         let id = self.id.clone();
         self.display.context.exec(proc(gl) {
             unsafe { gl.DeleteBuffers(1, [ id ].as_ptr()); }
         });
     }
 }
+
+/// A part of an index buffer, returned by `IndexBuffer::slice`.
+pub struct IndexBufferSlice<'a> {
+    buffer: &'a IndexBuffer,
+    first: uint,
+    count: uint,
+    base_vertex: int,
+}
+
+impl<'a> IndexBufferSlice<'a> {
+    /// Adds a value to each index before fetching the vertex.
+    ///
+    /// Not supported by GLES.
+    pub fn with_base_vertex(self, base_vertex: int) -> IndexBufferSlice<'a> {
+        IndexBufferSlice {
+            base_vertex: base_vertex,
+            .. self
+        }
+    }
+}
+
+impl<'a> IndicesSource for IndexBufferSlice<'a> {
+    fn to_indices_source_helper(&self) -> IndicesSourceHelper {
+        IndicesSourceHelper {
+            index_buffer: Some(self.buffer.id),
+            primitives: self.buffer.primitives,
+            data_type: self.buffer.data_type,
+            first: self.first,
+            count: Some(self.count),
+            base_vertex: self.base_vertex,
+        }
+    }
+}
+
+/// Draws the vertices of the vertex buffer in order, without an index buffer.
+///
+/// # Example
+///
+/// ```no_run
+/// # let vertex_buffer: simple_gl::VertexBuffer<u8> = unsafe { std::mem::uninitialized() };
+/// # let uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+/// # let mut target: simple_gl::Target = unsafe { std::mem::uninitialized() };
+/// target.draw(&(&vertex_buffer, &simple_gl::NoIndices(simple_gl::TriangleStrip), &uniforms));
+/// ```
+///
+pub struct NoIndices(pub PrimitiveType);
+
+impl IndicesSource for NoIndices {
+    fn to_indices_source_helper(&self) -> IndicesSourceHelper {
+        let &NoIndices(ref primitives) = self;
+
+        IndicesSourceHelper {
+            index_buffer: None,
+            primitives: primitives.get_gl_enum(),
+            data_type: 0,
+            first: 0,
+            count: None,
+            base_vertex: 0,
+        }
+    }
+}
+
+/// Objects that describe which vertices to draw.
+#[doc(hidden)]
+pub trait IndicesSource {
+    fn to_indices_source_helper(&self) -> IndicesSourceHelper;
+}
+
+/// This public struct is accessible from within `simple_gl` but not for the user.
+#[doc(hidden)]
+pub struct IndicesSourceHelper {
+    /// The index buffer, or `None` to draw the vertices in order.
+    pub index_buffer: Option<gl::types::GLuint>,
+    pub primitives: gl::types::GLenum,
+    /// Type of the indices. Unused if there is no index buffer.
+    pub data_type: gl::types::GLenum,
+    /// Position of the first index, or of the first vertex if there is no index buffer.
+    pub first: uint,
+    /// Number of indices, or `None` to draw all the vertices of the vertex buffer.
+    pub count: Option<uint>,
+    pub base_vertex: int,
+}
@@ -8,8 +8,8 @@ Easy-to-use high-level OpenGL3+ wrapper.
 
 # Initialization
 
-This library defines the `DisplayBuild` trait which is curently implemented only on
-`glutin::WindowBuilder`.
+This library defines the `DisplayBuild` trait which is curently implemented on
+`glutin::WindowBuilder` and, with the `headless` feature, on `glutin::HeadlessRendererBuilder`.
 
 Initialization is done by creating a `WindowBuilder` and calling `build_simple_gl`.
 
@@ -32,6 +32,21 @@ The `display` object is the most important object of this library.
 The window where you are drawing on will produce events. They can be received by calling
 `display.poll_events()`.
 
+## Headless rendering
+
+If the `headless` feature is enabled, `DisplayBuild` is also implemented on
+`glutin::HeadlessRendererBuilder`. The resulting `Display` doesn't need a window: everything
+drawn with `display.draw()` goes to an offscreen framebuffer, which is useful for tests running
+on machines without a screen.
+
+```ignore
+let display = glutin::HeadlessRendererBuilder::new(1024, 768)
+    .build_simple_gl().unwrap();
+```
+
+A headless `Display` never produces any event. The examples and the tests of this library
+render a single frame offscreen when they are built with `--features headless`.
+
 # Drawing
 
 Drawing something requires three elements:
@@ -68,6 +83,11 @@ let vertex_buffer = simple_gl::VertexBuffer::new(&display, vec![
 # }
 ```
 
+The elements of the struct can be floats, doubles or integers, or tuples and arrays of them.
+Integers are passed as integers to the shader, unless the element has the `#[normalized]`
+attribute, in which case they are converted to floats between `0.0` and `1.0` (or `-1.0` and
+`1.0` for signed integers). This is useful for colors stored as `[u8, ..4]`.
+
 ## Index buffer
 
 Creating an index buffer is done by calling `build_index_buffer` with an array containing
@@ -131,6 +151,34 @@ uniforms.set_value("uMatrix", [
 ]);
 ```
 
+Values that are shared between multiple programs can instead be stored in a `UniformBuffer`.
+Its content is a struct with the `#[uniform_block]` attribute, and it is bound to a
+`layout(std140)` uniform block of the program with `set_uniform_buffer`. Fields that are
+GLSL arrays must have the `#[array]` attribute.
+
+```no_run
+# #![feature(phase)]
+# #[phase(plugin)]
+# extern crate simple_gl_macros;
+# extern crate simple_gl;
+# fn main() {
+#[uniform_block]
+struct Light {
+    position: [f32, ..3],
+    color: [f32, ..3],
+}
+
+# let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+# let mut uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+let light = simple_gl::UniformBuffer::new(&display, Light {
+    position: [0.0, 10.0, 0.0],
+    color: [1.0, 1.0, 1.0],
+});
+
+uniforms.set_uniform_buffer("Light", &light);
+# }
+```
+
 ## Drawing
 
 Draw by calling `display.draw()`. This function call will return a `Target` object which can
@@ -150,6 +198,13 @@ target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
 target.finish();
 ```
 
+A `DrawParameters` object can be added to the tuple in order to configure the depth test,
+blending, backface culling, etc. Depth testing is disabled by default.
+
+Depth testing requires a depth buffer. Use `build_simple_gl_with_depth_stencil` to request one
+for the window, or `Texture::draw_with_attachments` with a `RenderBuffer` when drawing on a
+texture.
+
 */
 
 #[phase(plugin)]
@@ -164,27 +219,35 @@ extern crate native;
 extern crate time;
 
 #[doc(hidden)]
-pub use data_types::GLDataTuple;
+pub use data_types::{GLDataTuple, UniformValue};
+pub use framebuffer::{Framebuffer, FramebufferBuilder};
 
-pub use index_buffer::IndexBuffer;
-pub use vertex_buffer::{VertexBuffer, VertexBindings, VertexFormat};
+pub use index_buffer::{IndexBuffer, IndexBufferSlice, NoIndices, IndicesSource};
+pub use render_buffer::RenderBuffer;
+pub use vertex_buffer::{VertexBuffer, VertexBindings, VertexFormat, Mapping, InstancesList};
 pub use texture::Texture;
+pub use uniform_buffer::{UniformBuffer, UniformBlock, UniformBlockLayout};
 
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 mod context;
 mod data_types;
+mod framebuffer;
 mod index_buffer;
+mod render_buffer;
+mod sampler;
 mod texture;
+mod uniform_buffer;
+mod vertex_array_object;
 mod vertex_buffer;
 
 #[cfg(target_os = "windows")]
 #[cfg(target_os = "linux")]
 #[cfg(target_os = "macos")]
 mod gl {
-    generate_gl_bindings!("gl", "core", "3.3", "struct")
+    generate_gl_bindings!("gl", "core", "3.3", "struct", [ "GL_ARB_vertex_attrib_64bit" ])
 }
 
 #[cfg(target_os = "android")]
@@ -199,6 +262,9 @@ compile_error!("This platform is not supported")
 /// Something that can be drawn.
 pub trait Draw {
     /// Draws the object on the specified target.
+    ///
+    /// Fails if the object can't be drawn, for example if the vertex buffers don't match
+    /// the attributes of the program.
     fn draw(&self, &mut Target);
 }
 
@@ -251,7 +317,34 @@ impl PrimitiveType {
     }
 }
 
+/// Hint about how often the content of a buffer is going to be modified.
+///
+/// This doesn't restrict what you can do with the buffer, but choosing the right usage
+///  allows the driver to put the buffer in the most appropriate memory.
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// The content is written once and used many times. This is the default.
+    StaticDraw,
+
+    /// The content is modified often and used many times.
+    DynamicDraw,
+
+    /// The content is modified every time it is used, for example every frame.
+    StreamDraw,
+}
+
+impl BufferUsage {
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            StaticDraw => gl::STATIC_DRAW,
+            DynamicDraw => gl::DYNAMIC_DRAW,
+            StreamDraw => gl::STREAM_DRAW,
+        }
+    }
+}
+
 /// Function that the GPU will use for blending.
+#[deriving(Clone, Show, PartialEq, Eq)]
 pub enum BlendingFunction {
     /// Always replace the destination pixel by the source.
     AlwaysReplace,
@@ -266,6 +359,7 @@ pub enum BlendingFunction {
 /// Culling mode.
 /// 
 /// Describes how triangles could be filtered before the fragment part.
+#[deriving(Clone, Show, PartialEq, Eq)]
 pub enum BackfaceCullingMode {
     /// All triangles are always drawn.
     CullingDisabled,
@@ -280,6 +374,7 @@ pub enum BackfaceCullingMode {
 /// Function to use for out-of-bounds samples.
 ///
 /// This is how GL must handle samples that are outside the texture.
+#[deriving(Clone, Show, PartialEq, Eq)]
 pub enum SamplerWrapFunction {
     /// Samples at coord `x + 1` are mapped to coord `x`.
     Repeat,
@@ -288,10 +383,40 @@ pub enum SamplerWrapFunction {
     Mirror,
 
     /// Samples at coord `x + 1` are mapped to coord `1`.
-    Clamp
+    Clamp,
+
+    /// Samples outside the texture return the border color of the sampler.
+    ///
+    /// Not supported by GLES.
+    BorderClamp
+}
+
+impl SamplerWrapFunction {
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            Repeat => gl::REPEAT,
+            Mirror => gl::MIRRORED_REPEAT,
+            Clamp => gl::CLAMP_TO_EDGE,
+            BorderClamp => gl::CLAMP_TO_BORDER,
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            Repeat => gl::REPEAT,
+            Mirror => gl::MIRRORED_REPEAT,
+            Clamp => gl::CLAMP_TO_EDGE,
+            BorderClamp => fail!("Not supported by GLES"),
+        }
+    }
 }
 
 /// The function that the GPU will use when loading the value of a texel.
+#[deriving(Clone, Show, PartialEq, Eq)]
 pub enum SamplerFilter {
     /// The nearest texel will be loaded.
     Nearest,
@@ -300,8 +425,280 @@ pub enum SamplerFilter {
     Linear
 }
 
+impl SamplerFilter {
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            Nearest => gl::NEAREST,
+            Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// Describes how a shader must sample a texture.
+///
+/// Pass it to `ProgramUniforms::set_texture_with_sampler`:
+///
+/// ```no_run
+/// # let texture: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+/// # let mut uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+/// let behavior = simple_gl::SamplerBehavior {
+///     wrap_function: (simple_gl::Clamp, simple_gl::Clamp, simple_gl::Clamp),
+///     minify_filter: simple_gl::Nearest,
+///     magnify_filter: simple_gl::Nearest,
+///     .. std::default::Default::default()
+/// };
+///
+/// uniforms.set_texture_with_sampler("texture", &texture, &behavior);
+/// ```
+///
+#[deriving(Clone, Show, PartialEq)]
+pub struct SamplerBehavior {
+    /// Functions to use for the X, Y and Z coordinates.
+    pub wrap_function: (SamplerWrapFunction, SamplerWrapFunction, SamplerWrapFunction),
+
+    /// Filter to use when the texture is displayed smaller than its actual size.
+    pub minify_filter: SamplerFilter,
+
+    /// Filter to use when the texture is displayed bigger than its actual size.
+    pub magnify_filter: SamplerFilter,
+
+    /// Filter to use between mipmap levels when minifying, or `None` to only sample
+    ///  the first level.
+    pub mipmap_filter: Option<SamplerFilter>,
+
+    /// Maximum level of anisotropic filtering. `1` disables anisotropic filtering.
+    ///
+    /// The value is clamped to the maximum supported by the hardware, and ignored if the
+    ///  `GL_EXT_texture_filter_anisotropic` extension is not available.
+    pub max_anisotropy: u16,
+
+    /// Value added to the mipmap level computed by the GPU.
+    ///
+    /// Not supported by GLES.
+    pub lod_bias: f32,
+
+    /// Color returned for samples outside the texture when using `BorderClamp`.
+    pub border_color: (f32, f32, f32, f32),
+
+    /// If `Some`, the texture must be a depth texture and sampling it returns the result of
+    ///  the comparison between the reference value given by the shader and the texel, which
+    ///  requires a shadow sampler (like `sampler2DShadow`) in GLSL.
+    ///
+    /// Not supported by GLES.
+    pub depth_compare: Option<DepthFunction>,
+}
+
+impl std::default::Default for SamplerBehavior {
+    fn default() -> SamplerBehavior {
+        SamplerBehavior {
+            wrap_function: (Repeat, Repeat, Repeat),
+            minify_filter: Linear,
+            magnify_filter: Linear,
+            mipmap_filter: Some(Linear),
+            max_anisotropy: 1,
+            lod_bias: 0.0,
+            border_color: (0.0, 0.0, 0.0, 0.0),
+            depth_compare: None,
+        }
+    }
+}
+
+impl SamplerBehavior {
+    /// Returns the value of `TEXTURE_MIN_FILTER`.
+    fn get_minify_filter(&self) -> gl::types::GLenum {
+        match (self.minify_filter, self.mipmap_filter) {
+            (Nearest, None) => gl::NEAREST,
+            (Linear, None) => gl::LINEAR,
+            (Nearest, Some(Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Linear, Some(Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Nearest, Some(Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Linear, Some(Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+/// A face of a cubemap.
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub enum CubeMapFace {
+    /// The face in the direction of the positive X axis.
+    PositiveX,
+
+    /// The face in the direction of the negative X axis.
+    NegativeX,
+
+    /// The face in the direction of the positive Y axis.
+    PositiveY,
+
+    /// The face in the direction of the negative Y axis.
+    NegativeY,
+
+    /// The face in the direction of the positive Z axis.
+    PositiveZ,
+
+    /// The face in the direction of the negative Z axis.
+    NegativeZ,
+}
+
+impl CubeMapFace {
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            PositiveX => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            NegativeX => gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            PositiveY => gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+            NegativeY => gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+            PositiveZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+            NegativeZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+        }
+    }
+}
+
+/// Format in which the texels of a texture are stored in video memory.
+///
+/// Formats ending with `F` store floating-point values. Formats ending with `I` or `UI` store
+///  signed or unsigned integers, which must be sampled with an `isampler` or a `usampler`
+///  in GLSL. The other color formats store normalized values between 0.0 and 1.0.
+///
+/// Only `RGB8` and `RGBA8` are supported by GLES.
+#[allow(missing_doc)]
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8, RG8, RGB8, RGBA8,
+    R16, RG16, RGB16, RGBA16,
+    R16F, RG16F, RGB16F, RGBA16F,
+    R32F, RG32F, RGB32F, RGBA32F,
+    R11FG11FB10F,
+    R8I, RG8I, RGB8I, RGBA8I,
+    R16I, RG16I, RGB16I, RGBA16I,
+    R32I, RG32I, RGB32I, RGBA32I,
+    R8UI, RG8UI, RGB8UI, RGBA8UI,
+    R16UI, RG16UI, RGB16UI, RGBA16UI,
+    R32UI, RG32UI, RGB32UI, RGBA32UI,
+
+    /// sRGB color. The values are converted to linear RGB when they are sampled.
+    SRGB8,
+
+    /// sRGB color with a linear alpha component.
+    SRGB8Alpha8,
+
+    Depth16, Depth24, Depth32F,
+
+    /// Depth and stencil values. The data must be `u32`s containing the depth in their 24
+    ///  higher bits and the stencil value in their 8 lower bits.
+    Depth24Stencil8,
+}
+
+/// Error that can happen when creating a texture or a multisampled render buffer.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum TextureCreationError {
+    /// The format is not supported by the OpenGL context.
+    FormatNotSupported(TextureFormat),
+
+    /// The type of data is not compatible with the format. Contains the format and the
+    ///  number of components of the data.
+    IncompatibleDataType(TextureFormat, uint),
+
+    /// The number of samples of a multisampled texture or render buffer is `0` or is greater
+    ///  than the maximum supported by the implementation for this format. Contains the number
+    ///  of samples.
+    SamplesCountNotSupported(uint),
+}
+
+impl fmt::Show for TextureCreationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &FormatNotSupported(format) =>
+                write!(formatter, "The texture format `{}` is not supported by this context",
+                    format),
+            &IncompatibleDataType(format, components) =>
+                write!(formatter, "Data with {} components of this type can't be stored in a \
+                                   texture of format `{}`", components, format),
+            &SamplesCountNotSupported(samples) =>
+                write!(formatter, "Multisampled textures and render buffers with {} samples \
+                                   are not supported by this context", samples),
+        }
+    }
+}
+
+/// Error that can happen when drawing on attachments with a `FramebufferBuilder`, or when
+/// building a `Framebuffer`.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum FramebufferCreationError {
+    /// The framebuffer doesn't have any color texture.
+    FramebufferNoColorAttachment,
+
+    /// The attachments don't all have the same dimensions.
+    FramebufferDimensionsMismatch,
+
+    /// A texture attached as a depth and/or stencil buffer doesn't have a depth or stencil
+    ///  format.
+    FramebufferNotADepthTexture,
+
+    /// A cubemap is attached as a whole. Only its faces can be drawn on, with
+    ///  `Texture::draw_face`.
+    FramebufferCubemapAttachment,
+
+    /// One of the attachments can't be drawn on, for example because its format is not
+    ///  renderable.
+    FramebufferIncompleteAttachment,
+
+    /// The attachments don't all have the same number of samples.
+    FramebufferIncompleteMultisample,
+
+    /// The combination of formats of the attachments is not supported by the implementation.
+    FramebufferUnsupported,
+
+    /// Another reason. Contains the value returned by `glCheckFramebufferStatus`.
+    FramebufferIncomplete(u32),
+}
+
+impl fmt::Show for FramebufferCreationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &FramebufferNoColorAttachment =>
+                write!(formatter, "A framebuffer must have at least one color texture"),
+            &FramebufferDimensionsMismatch =>
+                write!(formatter, "All the attachments of a framebuffer must have the same \
+                                   dimensions"),
+            &FramebufferNotADepthTexture =>
+                write!(formatter, "Only depth and stencil textures can be attached as depth \
+                                   and stencil buffers"),
+            &FramebufferCubemapAttachment =>
+                write!(formatter, "Cubemaps can't be attached to a framebuffer, use \
+                                   `draw_face`"),
+            &FramebufferIncompleteAttachment =>
+                write!(formatter, "One of the attachments of the framebuffer can't be drawn on"),
+            &FramebufferIncompleteMultisample =>
+                write!(formatter, "The attachments of the framebuffer don't have the same \
+                                   number of samples"),
+            &FramebufferUnsupported =>
+                write!(formatter, "This combination of attachments is not supported by the \
+                                   implementation"),
+            &FramebufferIncomplete(status) =>
+                write!(formatter, "The framebuffer is incomplete (status 0x{:x})", status),
+        }
+    }
+}
+
+/// Error that can happen when unmapping a buffer.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum UnmapError {
+    /// The content of the buffer has been lost while it was mapped, for example because the
+    ///  screen mode changed. The buffer must be written again.
+    BufferContentLost,
+}
+
+impl fmt::Show for UnmapError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &BufferContentLost =>
+                write!(formatter, "The content of the buffer has been lost while it was mapped"),
+        }
+    }
+}
+
 /// The function that the GPU will use to determine whether to write over an existing pixel
 ///  on the target.
+#[deriving(Clone, Show, PartialEq, Eq)]
 pub enum DepthFunction {
     /// Never replace the target pixel.
     /// 
@@ -330,12 +727,220 @@ pub enum DepthFunction {
     IfLessOrEqual
 }
 
+impl DepthFunction {
+    fn get_gl_enum(&self) -> gl::types::GLenum {
+        match *self {
+            Ignore => gl::NEVER,
+            Overwrite => gl::ALWAYS,
+            IfEqual => gl::EQUAL,
+            IfNotEqual => gl::NOTEQUAL,
+            IfMore => gl::GREATER,
+            IfMoreOrEqual => gl::GEQUAL,
+            IfLess => gl::LESS,
+            IfLessOrEqual => gl::LEQUAL,
+        }
+    }
+}
+
+/// Defines how the device should render polygons.
+///
+/// Not supported by GLES, where polygons are always filled.
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub enum PolygonMode {
+    /// Only draw a single point at each vertex.
+    Point,
+
+    /// Only draw a line in the boundaries of each polygon.
+    Line,
+
+    /// Fill the content of the polygon. This is the default mode.
+    Fill
+}
+
+/// Represents the parameters to use when drawing.
+///
+/// Pass it alongside the vertex buffer, the index buffer and the uniforms in order to
+///  use it:
+///
+/// ```no_run
+/// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+/// # let vertex_buffer: simple_gl::VertexBuffer<u8> = unsafe { std::mem::uninitialized() };
+/// # let index_buffer: simple_gl::IndexBuffer = unsafe { std::mem::uninitialized() };
+/// # let uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+/// let params = simple_gl::DrawParameters {
+///     depth_function: simple_gl::IfLess,
+///     depth_write: true,
+///     .. std::default::Default::default()
+/// };
+///
+/// let mut target = display.draw();
+/// target.draw(&(&vertex_buffer, &index_buffer, &uniforms, &params));
+/// ```
+///
+#[deriving(Clone, Show, PartialEq)]
+pub struct DrawParameters {
+    /// The function that the GPU will use to merge the existing pixel with the pixel that is
+    ///  being written.
+    pub blending_function: BlendingFunction,
+
+    /// The function that the GPU will use to determine whether to write over an existing
+    ///  pixel on the target.
+    pub depth_function: DepthFunction,
+
+    /// Whether the depth value of the pixels that are written is stored in the depth buffer.
+    pub depth_write: bool,
+
+    /// Whether some triangles must be ignored depending on the order of their vertices.
+    pub backface_culling: BackfaceCullingMode,
+
+    /// How polygons are rendered.
+    pub polygon_mode: PolygonMode,
+
+    /// Width in pixels of the lines to draw. The default value is `1.0`.
+    pub line_width: f32,
+
+    /// Diameter in pixels of the points to draw, or `None` to let the vertex shader write
+    ///  `gl_PointSize`. The default value is `Some(1.0)`.
+    ///
+    /// GLES always uses `gl_PointSize`, so the only other value supported there is `Some(1.0)`,
+    ///  which has no effect.
+    pub point_size: Option<f32>,
+}
+
+impl std::default::Default for DrawParameters {
+    fn default() -> DrawParameters {
+        DrawParameters {
+            blending_function: LerpBySourceAlpha,
+            depth_function: Overwrite,
+            depth_write: false,
+            backface_culling: CullingDisabled,
+            polygon_mode: Fill,
+            line_width: 1.0,
+            point_size: Some(1.0),
+        }
+    }
+}
+
+impl DrawParameters {
+    /// Synchronizes the parameters with the current OpenGL state.
+    fn sync(&self, gl: &gl::Gl) {
+        // blending
+        match self.blending_function {
+            AlwaysReplace => gl.Disable(gl::BLEND),
+            LerpBySourceAlpha => {
+                gl.Enable(gl::BLEND);
+                gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            },
+            LerpByDestinationAlpha => {
+                gl.Enable(gl::BLEND);
+                gl.BlendFunc(gl::DST_ALPHA, gl::ONE_MINUS_DST_ALPHA);
+            },
+        }
+
+        // depth test ; depth values are not written when the test is disabled, so we only
+        //  disable it if it would always pass and nothing has to be written
+        if self.depth_function == Overwrite && !self.depth_write {
+            gl.Disable(gl::DEPTH_TEST);
+        } else {
+            gl.Enable(gl::DEPTH_TEST);
+            gl.DepthFunc(self.depth_function.get_gl_enum());
+        }
+        gl.DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE });
+
+        // backface culling
+        match self.backface_culling {
+            CullingDisabled => gl.Disable(gl::CULL_FACE),
+            CullCounterClockWise => {
+                gl.Enable(gl::CULL_FACE);
+                gl.FrontFace(gl::CCW);
+                gl.CullFace(gl::FRONT);
+            },
+            CullClockWise => {
+                gl.Enable(gl::CULL_FACE);
+                gl.FrontFace(gl::CCW);
+                gl.CullFace(gl::BACK);
+            },
+        }
+
+        // line width
+        gl.LineWidth(self.line_width);
+
+        sync_polygon_mode_and_point_size(gl, self.polygon_mode, self.point_size);
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn sync_polygon_mode_and_point_size(gl: &gl::Gl, polygon_mode: PolygonMode,
+                                    point_size: Option<f32>)
+{
+    gl.PolygonMode(gl::FRONT_AND_BACK, match polygon_mode {
+        Point => gl::POINT,
+        Line => gl::LINE,
+        Fill => gl::FILL,
+    });
+
+    match point_size {
+        Some(size) => {
+            gl.Disable(gl::PROGRAM_POINT_SIZE);
+            gl.PointSize(size);
+        },
+        None => gl.Enable(gl::PROGRAM_POINT_SIZE)
+    }
+}
+
+#[cfg(target_os = "android")]
+fn sync_polygon_mode_and_point_size(_: &gl::Gl, polygon_mode: PolygonMode,
+                                    point_size: Option<f32>)
+{
+    if polygon_mode != Fill {
+        fail!("Polygon modes are not supported by GLES");
+    }
+
+    match point_size {
+        Some(size) if size != 1.0 =>
+            fail!("GLES only supports writing gl_PointSize in the vertex shader"),
+        _ => ()
+    }
+}
+
+/// Area of a target, in pixels.
+///
+/// The origin is the bottom-left corner of the target.
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub struct Rect {
+    /// Number of pixels between the left border of the target and the area.
+    pub left: uint,
+    /// Number of pixels between the bottom border of the target and the area.
+    pub bottom: uint,
+    /// Width of the area.
+    pub width: uint,
+    /// Height of the area.
+    pub height: uint,
+}
+
+/// The buffers that `Target::blit` must copy.
+#[deriving(Clone, Show, PartialEq, Eq)]
+pub struct BlitMask {
+    /// Copy the first color attachment of the source to the color attachments of the
+    /// destination.
+    pub color: bool,
+    /// Copy the depth buffer.
+    pub depth: bool,
+    /// Copy the stencil buffer.
+    pub stencil: bool,
+}
+
 /// A target where things can be drawn.
 pub struct Target<'t> {
     display: Arc<DisplayImpl>,
     display_hold: Option<&'t Display>,
-    texture: Option<&'t mut Texture>,
-    framebuffer: Option<FrameBufferObject>,
+    textures: Vec<&'t Texture>,
+    dimensions: (uint, uint),
+    viewport: Option<Rect>,
+    scissor: Option<Rect>,
+    framebuffer: Option<Arc<framebuffer::FrameBufferObject>>,
     execute_end: Option<proc(&DisplayImpl):Send>,
 }
 
@@ -345,82 +950,614 @@ impl<'t> Target<'t> {
     }
 
     /// Draws.
+    ///
+    /// Fails if the vertex buffers don't provide all the attributes of the program with the
+    /// right types. Use `Program::check_vertex_buffers` to handle this error instead, for
+    /// example right after loading the shaders.
     pub fn draw<D: Draw>(&mut self, object: &D) {
         object.draw(self);
     }
+
+    /// Clears the color attachments of the target with the given color.
+    ///
+    /// Only the scissor box is cleared if there is one.
+    pub fn clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+        let state = self.get_state();
+
+        self.display.context.exec(proc(gl) {
+            bind_target_state(gl, &state);
+            gl.ClearColor(red, green, blue, alpha);
+            gl.Clear(gl::COLOR_BUFFER_BIT);
+        });
+    }
+
+    /// Clears the depth buffer of the target with the given value.
+    ///
+    /// Does nothing if the target doesn't have a depth buffer. Only the scissor box is cleared
+    /// if there is one.
+    pub fn clear_depth(&mut self, value: f32) {
+        let state = self.get_state();
+
+        self.display.context.exec(proc(gl) {
+            bind_target_state(gl, &state);
+            gl.DepthMask(gl::TRUE);
+            clear_depth_value(gl, value);
+            gl.Clear(gl::DEPTH_BUFFER_BIT);
+        });
+    }
+
+    /// Clears the stencil buffer of the target with the given value.
+    ///
+    /// Does nothing if the target doesn't have a stencil buffer. Only the scissor box is
+    /// cleared if there is one.
+    pub fn clear_stencil(&mut self, value: int) {
+        let state = self.get_state();
+
+        self.display.context.exec(proc(gl) {
+            bind_target_state(gl, &state);
+            gl.StencilMask(0xffffffff);
+            gl.ClearStencil(value as gl::types::GLint);
+            gl.Clear(gl::STENCIL_BUFFER_BIT);
+        });
+    }
+
+    /// Returns the width and height of the target, in pixels.
+    pub fn get_dimensions(&self) -> (uint, uint) {
+        self.dimensions
+    }
+
+    /// Sets the area of the target where the next draws will be rendered, or the whole
+    /// target if `None`.
+    ///
+    /// The normalized device coordinates of the vertices are mapped to this rectangle. The
+    /// viewport is the whole target by default.
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        self.viewport = viewport;
+    }
+
+    /// Restricts the next draws and clears to an area of the target, or removes the
+    /// restriction if `None`.
+    ///
+    /// Unlike the viewport, the scissor box doesn't change the position of what is drawn: the
+    /// pixels outside of the box are simply left untouched.
+    pub fn set_scissor(&mut self, scissor: Option<Rect>) {
+        self.scissor = scissor;
+    }
+
+    /// Returns the framebuffer, the viewport and the scissor box that must be used by the
+    /// commands executed on this target.
+    fn get_state(&self) -> (gl::types::GLuint, Rect, Option<Rect>) {
+        let (width, height) = self.dimensions;
+        let viewport = self.viewport.clone()
+            .unwrap_or(Rect { left: 0, bottom: 0, width: width, height: height });
+
+        (self.get_framebuffer_id(), viewport, self.scissor.clone())
+    }
+
+    /// Returns the framebuffer object to bind in order to draw on this target, or `0` for the
+    /// default framebuffer.
+    fn get_framebuffer_id(&self) -> gl::types::GLuint {
+        match self.framebuffer {
+            Some(ref framebuffer) => framebuffer.id,
+            None => self.display.context.get_default_framebuffer()
+        }
+    }
+
+    /// Copies the content of the first color attachment of this target to another target.
+    ///
+    /// If this target is multisampled, the samples of each pixel are combined in order to
+    /// obtain an antialiased result. The destination can be a texture or the display, and
+    /// must have the same dimensions as this target.
+    ///
+    /// This is the same as calling `blit` with the whole target as source and destination.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// # let mut multisampled: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+    /// let target = multisampled.draw();
+    /// // ...
+    /// target.resolve(&mut display.draw());
+    /// ```
+    pub fn resolve(&self, destination: &mut Target) {
+        if self.dimensions != destination.dimensions {
+            fail!("The destination of a resolve must have the same dimensions as the source");
+        }
+
+        let (width, height) = self.dimensions;
+        let rect = Rect { left: 0, bottom: 0, width: width, height: height };
+
+        self.blit(&rect, destination, &rect,
+            &BlitMask { color: true, depth: false, stencil: false }, Nearest);
+    }
+
+    /// Copies a rectangle of this target to a rectangle of another target.
+    ///
+    /// If the rectangles don't have the same dimensions, the content is stretched using the
+    /// given filter. Depth and stencil buffers can only be copied with the `Nearest` filter,
+    /// and require both targets to have buffers of the same format. The destination can be
+    /// a texture or the display.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// # let mut texture: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+    /// let source = texture.draw();
+    /// let mut destination = display.draw();
+    ///
+    /// // copying the texture in the bottom-left quarter of the window
+    /// let (width, height) = destination.get_dimensions();
+    /// source.blit(&simple_gl::Rect { left: 0, bottom: 0, width: 512, height: 512 },
+    ///     &mut destination,
+    ///     &simple_gl::Rect { left: 0, bottom: 0, width: width / 2, height: height / 2 },
+    ///     &simple_gl::BlitMask { color: true, depth: false, stencil: false },
+    ///     simple_gl::Linear);
+    /// ```
+    pub fn blit(&self, source_rect: &Rect, destination: &mut Target, destination_rect: &Rect,
+                mask: &BlitMask, filter: SamplerFilter)
+    {
+        if !is_rect_inside(source_rect, self.dimensions) {
+            fail!("The source rectangle is out of the bounds of the target");
+        }
+
+        if !is_rect_inside(destination_rect, destination.dimensions) {
+            fail!("The destination rectangle is out of the bounds of the target");
+        }
+
+        if (mask.depth || mask.stencil) && filter != Nearest {
+            fail!("Depth and stencil buffers can only be copied with the `Nearest` filter");
+        }
+
+        let mut gl_mask = 0;
+        if mask.color { gl_mask |= gl::COLOR_BUFFER_BIT; }
+        if mask.depth { gl_mask |= gl::DEPTH_BUFFER_BIT; }
+        if mask.stencil { gl_mask |= gl::STENCIL_BUFFER_BIT; }
+
+        let source_id = self.get_framebuffer_id();
+        let destination_id = destination.get_framebuffer_id();
+        let source_rect = source_rect.clone();
+        let destination_rect = destination_rect.clone();
+        let filter = filter.get_gl_enum();
+
+        self.display.context.exec(proc(gl) {
+            blit_framebuffer(gl, source_id, destination_id, &source_rect, &destination_rect,
+                gl_mask, filter);
+        });
+    }
 }
 
-impl<'a, 'b, 'c, V> Draw for (&'a VertexBuffer<V>, &'b IndexBuffer, &'c ProgramUniforms) {
-    fn draw(&self, target: &mut Target) {
-        let &(vertex_buffer, index_buffer, program) = self;
+/// Binds the framebuffer returned by `Target::get_state` and sets its viewport and
+/// scissor box.
+fn bind_target_state(gl: &gl::Gl, state: &(gl::types::GLuint, Rect, Option<Rect>)) {
+    let &(framebuffer, ref viewport, ref scissor) = state;
+
+    gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+    gl.Viewport(viewport.left as gl::types::GLint, viewport.bottom as gl::types::GLint,
+        viewport.width as gl::types::GLsizei, viewport.height as gl::types::GLsizei);
+
+    match scissor {
+        &Some(ref scissor) => {
+            gl.Enable(gl::SCISSOR_TEST);
+            gl.Scissor(scissor.left as gl::types::GLint, scissor.bottom as gl::types::GLint,
+                scissor.width as gl::types::GLsizei, scissor.height as gl::types::GLsizei);
+        },
+        &None => gl.Disable(gl::SCISSOR_TEST)
+    }
+}
 
-        let fbo_id = target.framebuffer.as_ref().map(|f| f.id);
-        let (vb_id, vb_elementssize, vb_bindingsclone) = vertex_buffer::get_clone(vertex_buffer);
-        let (ib_id, ib_elemcounts, ib_datatype, ib_primitives) = index_buffer::get_clone(index_buffer);
-        let program_id = program.program.id.clone();
-        let uniforms_clone = program.clone();
+/// Returns true if the rectangle is inside a target of the given dimensions.
+fn is_rect_inside(rect: &Rect, (width, height): (uint, uint)) -> bool {
+    rect.left + rect.width <= width && rect.bottom + rect.height <= height
+}
 
-        target.display.context.exec(proc(gl) {
-            unsafe {
-                gl.BindFramebuffer(gl::FRAMEBUFFER, fbo_id.unwrap_or(0));
+/// Copies a rectangle of a framebuffer to a rectangle of another framebuffer.
+///
+/// `0` designates the default framebuffer.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn blit_framebuffer(gl: &gl::Gl, source: gl::types::GLuint, destination: gl::types::GLuint,
+                    source_rect: &Rect, destination_rect: &Rect, mask: gl::types::GLbitfield,
+                    filter: gl::types::GLenum)
+{
+    gl.BindFramebuffer(gl::READ_FRAMEBUFFER, source);
+    gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, destination);
+
+    // the scissor test also applies to blitting
+    gl.Disable(gl::SCISSOR_TEST);
+
+    gl.BlitFramebuffer(source_rect.left as gl::types::GLint,
+        source_rect.bottom as gl::types::GLint,
+        (source_rect.left + source_rect.width) as gl::types::GLint,
+        (source_rect.bottom + source_rect.height) as gl::types::GLint,
+        destination_rect.left as gl::types::GLint,
+        destination_rect.bottom as gl::types::GLint,
+        (destination_rect.left + destination_rect.width) as gl::types::GLint,
+        (destination_rect.bottom + destination_rect.height) as gl::types::GLint,
+        mask, filter);
+}
 
-                gl.Disable(gl::DEPTH_TEST);
-                gl.Enable(gl::BLEND);
-                gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+/// Copies a rectangle of a framebuffer to a rectangle of another framebuffer.
+#[cfg(target_os = "android")]
+fn blit_framebuffer(_: &gl::Gl, _: gl::types::GLuint, _: gl::types::GLuint, _: &Rect, _: &Rect,
+                    _: gl::types::GLbitfield, _: gl::types::GLenum)
+{
+    fail!("Copying between framebuffers is not supported by GLES")
+}
 
-                // binding program
-                gl.UseProgram(program_id);
+/// Sets the value used when clearing the depth buffer.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn clear_depth_value(gl: &gl::Gl, value: f32) {
+    gl.ClearDepth(value as gl::types::GLclampd);
+}
 
-                // binding program uniforms
-                {
-                    let mut active_texture: uint = 0;
-                    for (&location, ref texture) in uniforms_clone.textures.iter() {
-                        gl.ActiveTexture(gl::TEXTURE0 + active_texture as u32);
-                        gl.BindTexture(texture.bind_point, texture.id);
-                        gl.Uniform1i(location, active_texture as i32);
-                        active_texture = active_texture + 1;
-                    }
+/// Sets the value used when clearing the depth buffer.
+#[cfg(target_os = "android")]
+fn clear_depth_value(gl: &gl::Gl, value: f32) {
+    gl.ClearDepthf(value);
+}
 
-                    for (&location, &(ref datatype, ref data)) in uniforms_clone.values.iter() {
-                        match *datatype {
-                            gl::FLOAT       => gl.Uniform1fv(location, 1, data.as_ptr() as *const f32),
-                            gl::FLOAT_MAT4  => gl.UniformMatrix4fv(location, 1, 0, data.as_ptr() as *const f32),
-                            _ => fail!("Loading uniforms for this type not implemented")
-                        }
-                        //gl.Uniform1i(location, active_texture as i32);
-                    }
+/// A depth and/or stencil buffer that can be attached when drawing on a texture.
+///
+/// See `Texture::draw_with_attachments` and `FramebufferBuilder::with_depth_stencil_attachment`.
+pub enum DepthStencilAttachment<'a> {
+    /// A render buffer. Its kind (depth, stencil or depth-stencil) determines where it is
+    /// attached.
+    RenderBufferAttachment(&'a RenderBuffer),
+
+    /// A texture created with `Texture::new_depth`, used as a depth buffer.
+    DepthTextureAttachment(&'a Texture),
+}
+
+impl<'a, 'b, 'c, V, I: IndicesSource> Draw for (&'a VertexBuffer<V>, &'b I, &'c ProgramUniforms) {
+    fn draw(&self, target: &mut Target) {
+        let &(vertex_buffer, indices, program) = self;
+        draw_impl(target, vertex_buffer, None, indices, program,
+            &std::default::Default::default());
+    }
+}
+
+impl<'a, 'b, 'c, 'd, V, I: IndicesSource> Draw for (&'a VertexBuffer<V>, &'b I,
+                                                    &'c ProgramUniforms, &'d DrawParameters)
+{
+    fn draw(&self, target: &mut Target) {
+        let &(vertex_buffer, indices, program, params) = self;
+        draw_impl(target, vertex_buffer, None, indices, program, params);
+    }
+}
+
+impl<'a, 'b, 'c, 'd, 'e, V, I: IndicesSource> Draw for (&'a VertexBuffer<V>, &'b InstancesList<'e>,
+                                                        &'c I, &'d ProgramUniforms)
+{
+    fn draw(&self, target: &mut Target) {
+        let &(vertex_buffer, instances, indices, program) = self;
+        draw_impl(target, vertex_buffer, Some(instances), indices, program,
+            &std::default::Default::default());
+    }
+}
+
+impl<'a, 'b, 'c, 'd, 'e, 'f, V, I: IndicesSource> Draw for (&'a VertexBuffer<V>,
+                                                            &'b InstancesList<'f>, &'c I,
+                                                            &'d ProgramUniforms,
+                                                            &'e DrawParameters)
+{
+    fn draw(&self, target: &mut Target) {
+        let &(vertex_buffer, instances, indices, program, params) = self;
+        draw_impl(target, vertex_buffer, Some(instances), indices, program, params);
+    }
+}
+
+/// Draws a vertex buffer with a program on a target, optionally multiple times.
+fn draw_impl<V, I: IndicesSource>(target: &mut Target, vertex_buffer: &VertexBuffer<V>,
+                                  instances: Option<&InstancesList>, indices: &I,
+                                  program: &ProgramUniforms, draw_parameters: &DrawParameters)
+{
+    let target_state = target.get_state();
+    let colors_count = target.framebuffer.as_ref().map(|f| f.outputs.len()).unwrap_or(0);
+    let indices = indices.to_indices_source_helper();
+    let vertices_count = vertex_buffer.len();
+    let program_id = program.program.id.clone();
+    let program_attributes = program.program.attributes.clone();
+    let uniforms_clone = program.clone();
+    let draw_parameters = draw_parameters.clone();
+    let max_anisotropy = target.display.max_anisotropy;
+
+    let (vertex_buffers, instances_count) = get_vertex_buffers_list(vertex_buffer, instances);
+
+    let vao_id = match vertex_array_object::get_vertex_array_object(&target.display, program_id,
+        &program_attributes, &vertex_buffers, indices.index_buffer.unwrap_or(0))
+    {
+        Ok(id) => id,
+        Err(err) => fail!("{}", err)
+    };
+
+    let draw_buffers = match target.framebuffer {
+        Some(ref fbo) => framebuffer::get_draw_buffers(&target.display, program_id, &**fbo),
+        None => None
+    };
+
+    target.display.context.exec(proc(gl) {
+        unsafe {
+            bind_target_state(gl, &target_state);
+
+            draw_parameters.sync(gl);
+
+            // binding program
+            gl.UseProgram(program_id);
+            framebuffer::bind_fragment_outputs(gl, &draw_buffers);
+
+            // binding program uniforms
+            {
+                let mut active_texture: uint = 0;
+                for (&location, &(ref texture, ref sampler)) in uniforms_clone.textures.iter() {
+                    gl.ActiveTexture(gl::TEXTURE0 + active_texture as u32);
+                    gl.BindTexture(texture.bind_point, texture.id);
+                    sampler::bind_sampler(gl, active_texture as gl::types::GLuint,
+                        texture.bind_point, sampler.as_ref().map(|s| &**s), max_anisotropy);
+                    gl.Uniform1i(location, active_texture as i32);
+                    active_texture = active_texture + 1;
                 }
 
-                // binding buffers
-                gl.BindBuffer(gl::ARRAY_BUFFER, vb_id);
-                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ib_id);
-
-                // binding vertex buffer
-                let mut locations = Vec::new();
-                for (name, &(data_type, data_size, data_offset)) in vb_bindingsclone.iter() {
-                    let loc = gl.GetAttribLocation(program_id, name.to_c_str().unwrap());
-                    locations.push(loc);
-
-                    if loc != -1 {
-                        match data_type {
-                            gl::BYTE | gl::UNSIGNED_BYTE | gl::SHORT | gl::UNSIGNED_SHORT | gl::INT | gl::UNSIGNED_INT
-                                => fail!("Not supported"), // TODO: gl.VertexAttribIPointer(loc as u32, data_size, data_type, vb_elementssize as i32, data_offset as *const libc::c_void),
-                            _ => gl.VertexAttribPointer(loc as u32, data_size, data_type, 0, vb_elementssize as i32, data_offset as *const libc::c_void)
-                        }
-                        
-                        gl.EnableVertexAttribArray(loc as u32);
-                    }
+                for &(location, datatype, count, ref data) in uniforms_clone.values.iter() {
+                    bind_uniform_value(gl, location, datatype, count, data);
                 }
-                
-                // drawing
-                gl.DrawElements(ib_primitives, ib_elemcounts as i32, ib_datatype, std::ptr::null());
 
-                // disable vertex attrib array
-                for l in locations.iter() {
-                    gl.DisableVertexAttribArray(l.clone() as u32);
+                bind_uniform_buffers(gl, &uniforms_clone.uniform_buffers);
+            }
+
+            // binding vertex buffers and index buffer
+            let locations = vertex_array_object::bind_vertex_array_object(gl, vao_id,
+                &*program_attributes, &vertex_buffers, indices.index_buffer.unwrap_or(0));
+
+            // drawing
+            draw_call(gl, &indices, vertices_count, instances_count);
+
+            vertex_array_object::unbind_vertex_array_object(gl, locations.as_slice());
+            framebuffer::unbind_fragment_outputs(gl, &draw_buffers, colors_count);
+        }
+    }).get();
+}
+
+/// Returns the list of vertex buffers to bind for a draw, and the number of instances.
+///
+/// The list contains the per-vertex buffer followed by the per-instance buffers.
+fn get_vertex_buffers_list<V>(vertex_buffer: &VertexBuffer<V>, instances: Option<&InstancesList>)
+    -> (Vec<vertex_array_object::VertexBufferBinding>, Option<uint>)
+{
+    let mut vertex_buffers = Vec::new();
+    let (vb_id, vb_elementssize, vb_bindingsclone) = vertex_buffer::get_clone(vertex_buffer);
+    vertex_buffers.push((vb_id, vb_elementssize, vb_bindingsclone, 0));
+
+    let instances_count = match instances {
+        Some(instances) => {
+            let (buffers, count) = vertex_buffer::get_instances_clone(instances);
+            for (id, elements_size, bindings) in buffers.move_iter() {
+                vertex_buffers.push((id, elements_size, bindings, 1));
+            }
+            Some(count)
+        },
+        None => None
+    };
+
+    (vertex_buffers, instances_count)
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn vertex_attrib_divisor(gl: &gl::Gl, location: gl::types::GLuint, divisor: gl::types::GLuint) {
+    gl.VertexAttribDivisor(location, divisor);
+}
+
+#[cfg(target_os = "android")]
+fn vertex_attrib_divisor(_: &gl::Gl, _: gl::types::GLuint, _: gl::types::GLuint) {
+    fail!("Instancing is not supported by GLES")
+}
+
+/// Calls the appropriate draw function. The vertex and index buffers must already be bound.
+///
+/// `vertices_count` is the number of vertices of the vertex buffer, and `instances` the
+///  number of instances to draw if instancing is used.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn draw_call(gl: &gl::Gl, indices: &index_buffer::IndicesSourceHelper, vertices_count: uint,
+             instances: Option<uint>)
+{
+    let primitives = indices.primitives;
+
+    match indices.index_buffer {
+        Some(_) => {
+            let count = indices.count.unwrap() as gl::types::GLsizei;
+            let offset = (indices.first * get_index_size(indices.data_type)) as *const libc::c_void;
+            let base_vertex = indices.base_vertex as gl::types::GLint;
+
+            unsafe {
+                match (instances, base_vertex) {
+                    (None, 0) => gl.DrawElements(primitives, count, indices.data_type, offset),
+                    (None, _) => gl.DrawElementsBaseVertex(primitives, count, indices.data_type,
+                        offset, base_vertex),
+                    (Some(instances), 0) => gl.DrawElementsInstanced(primitives, count,
+                        indices.data_type, offset, instances as gl::types::GLsizei),
+                    (Some(instances), _) => gl.DrawElementsInstancedBaseVertex(primitives, count,
+                        indices.data_type, offset, instances as gl::types::GLsizei, base_vertex),
                 }
             }
-        }).get();
+        },
+
+        None => {
+            let first = indices.first as gl::types::GLint;
+            let count = indices.count.unwrap_or(vertices_count) as gl::types::GLsizei;
+
+            match instances {
+                None => gl.DrawArrays(primitives, first, count),
+                Some(instances) => gl.DrawArraysInstanced(primitives, first, count,
+                    instances as gl::types::GLsizei),
+            }
+        },
+    }
+}
+
+/// Calls the appropriate draw function. The vertex and index buffers must already be bound.
+#[cfg(target_os = "android")]
+fn draw_call(gl: &gl::Gl, indices: &index_buffer::IndicesSourceHelper, vertices_count: uint,
+             instances: Option<uint>)
+{
+    if instances.is_some() {
+        fail!("Instancing is not supported by GLES");
+    }
+
+    if indices.base_vertex != 0 {
+        fail!("Base vertex is not supported by GLES");
+    }
+
+    match indices.index_buffer {
+        Some(_) => {
+            let count = indices.count.unwrap() as gl::types::GLsizei;
+            let offset = (indices.first * get_index_size(indices.data_type)) as *const libc::c_void;
+
+            unsafe {
+                gl.DrawElements(indices.primitives, count, indices.data_type, offset);
+            }
+        },
+
+        None => {
+            let count = indices.count.unwrap_or(vertices_count) as gl::types::GLsizei;
+            gl.DrawArrays(indices.primitives, indices.first as gl::types::GLint, count);
+        },
+    }
+}
+
+/// Returns the size in bytes of an index.
+fn get_index_size(data_type: gl::types::GLenum) -> uint {
+    match data_type {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+/// Sends the value of a uniform to the program currently in use.
+///
+/// `count` is the number of consecutive array elements contained in `data`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+unsafe fn bind_uniform_value(gl: &gl::Gl, location: gl::types::GLint,
+                             datatype: gl::types::GLenum, count: gl::types::GLsizei,
+                             data: &Vec<char>)
+{
+    let ptr = data.as_ptr();
+
+    match datatype {
+        gl::FLOAT               => gl.Uniform1fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC2          => gl.Uniform2fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC3          => gl.Uniform3fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC4          => gl.Uniform4fv(location, count, ptr as *const f32),
+        gl::INT                 => gl.Uniform1iv(location, count, ptr as *const i32),
+        gl::INT_VEC2            => gl.Uniform2iv(location, count, ptr as *const i32),
+        gl::INT_VEC3            => gl.Uniform3iv(location, count, ptr as *const i32),
+        gl::INT_VEC4            => gl.Uniform4iv(location, count, ptr as *const i32),
+        gl::UNSIGNED_INT        => gl.Uniform1uiv(location, count, ptr as *const u32),
+        gl::UNSIGNED_INT_VEC2   => gl.Uniform2uiv(location, count, ptr as *const u32),
+        gl::UNSIGNED_INT_VEC3   => gl.Uniform3uiv(location, count, ptr as *const u32),
+        gl::UNSIGNED_INT_VEC4   => gl.Uniform4uiv(location, count, ptr as *const u32),
+        gl::BOOL | gl::BOOL_VEC2 | gl::BOOL_VEC3 | gl::BOOL_VEC4
+            => bind_bool_uniform_value(gl, location, datatype, count, data),
+        gl::FLOAT_MAT2          => gl.UniformMatrix2fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT3          => gl.UniformMatrix3fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT4          => gl.UniformMatrix4fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT2x3        => gl.UniformMatrix2x3fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT2x4        => gl.UniformMatrix2x4fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT3x2        => gl.UniformMatrix3x2fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT3x4        => gl.UniformMatrix3x4fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT4x2        => gl.UniformMatrix4x2fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT4x3        => gl.UniformMatrix4x3fv(location, count, 0, ptr as *const f32),
+        _ => fail!("Loading uniforms for this type not implemented")
+    }
+}
+
+/// Sends the value of a uniform to the program currently in use.
+///
+/// `count` is the number of consecutive array elements contained in `data`.
+#[cfg(target_os = "android")]
+unsafe fn bind_uniform_value(gl: &gl::Gl, location: gl::types::GLint,
+                             datatype: gl::types::GLenum, count: gl::types::GLsizei,
+                             data: &Vec<char>)
+{
+    let ptr = data.as_ptr();
+
+    match datatype {
+        gl::FLOAT               => gl.Uniform1fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC2          => gl.Uniform2fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC3          => gl.Uniform3fv(location, count, ptr as *const f32),
+        gl::FLOAT_VEC4          => gl.Uniform4fv(location, count, ptr as *const f32),
+        gl::INT                 => gl.Uniform1iv(location, count, ptr as *const i32),
+        gl::INT_VEC2            => gl.Uniform2iv(location, count, ptr as *const i32),
+        gl::INT_VEC3            => gl.Uniform3iv(location, count, ptr as *const i32),
+        gl::INT_VEC4            => gl.Uniform4iv(location, count, ptr as *const i32),
+        gl::BOOL | gl::BOOL_VEC2 | gl::BOOL_VEC3 | gl::BOOL_VEC4
+            => bind_bool_uniform_value(gl, location, datatype, count, data),
+        gl::FLOAT_MAT2          => gl.UniformMatrix2fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT3          => gl.UniformMatrix3fv(location, count, 0, ptr as *const f32),
+        gl::FLOAT_MAT4          => gl.UniformMatrix4fv(location, count, 0, ptr as *const f32),
+        _ => fail!("Loading uniforms for this type not implemented")
+    }
+}
+
+/// Binds the uniform buffers to the blocks of the program currently in use.
+///
+/// Each block of a program uses the binding point whose index is the index of the block,
+/// which is set when the program is created.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn bind_uniform_buffers(gl: &gl::Gl,
+                        buffers: &HashMap<gl::types::GLuint, Arc<uniform_buffer::UniformBufferImpl>>)
+{
+    for (&block_index, buffer) in buffers.iter() {
+        gl.BindBufferBase(gl::UNIFORM_BUFFER, block_index, buffer.id);
+    }
+}
+
+/// Binds the uniform buffers to the blocks of the program currently in use.
+#[cfg(target_os = "android")]
+fn bind_uniform_buffers(_: &gl::Gl,
+                        buffers: &HashMap<gl::types::GLuint, Arc<uniform_buffer::UniformBufferImpl>>)
+{
+    // uniform blocks don't exist in GLES 2
+    assert!(buffers.is_empty());
+}
+
+/// Sends the value of a `bool` or `bvec` uniform to the program currently in use.
+///
+/// Rust booleans are one byte each, so they have to be converted to integers first.
+unsafe fn bind_bool_uniform_value(gl: &gl::Gl, location: gl::types::GLint,
+                                  datatype: gl::types::GLenum, count: gl::types::GLsizei,
+                                  data: &Vec<char>)
+{
+    let components = match datatype {
+        gl::BOOL => 1,
+        gl::BOOL_VEC2 => 2,
+        gl::BOOL_VEC3 => 3,
+        gl::BOOL_VEC4 => 4,
+        _ => unreachable!()
+    };
+
+    let bytes = data.as_ptr() as *const u8;
+    let values: Vec<gl::types::GLint> = range(0, components * count as uint)
+        .map(|i| if *bytes.offset(i as int) != 0 { 1 } else { 0 })
+        .collect();
+
+    match components {
+        1 => gl.Uniform1iv(location, count, values.as_ptr()),
+        2 => gl.Uniform2iv(location, count, values.as_ptr()),
+        3 => gl.Uniform3iv(location, count, values.as_ptr()),
+        _ => gl.Uniform4iv(location, count, values.as_ptr()),
     }
 }
 
@@ -487,10 +1624,37 @@ impl Program {
             display: self.program.display.clone(),
             program: self.program.clone(),
             textures: HashMap::new(),
-            values: HashMap::new(),
-            uniforms: self.program.uniforms.clone()
+            values: Vec::new(),
+            uniform_buffers: HashMap::new(),
+            uniforms: self.program.uniforms.clone(),
+            strict: false,
         }
     }
+
+    /// Returns the number of elements of a uniform, or `None` if the program doesn't contain
+    /// a uniform with this name.
+    ///
+    /// Uniforms that are not arrays have a size of 1. Individual elements of arrays,
+    /// like `lights[2]`, also have a size of 1.
+    pub fn get_uniform_array_size(&self, uniform_name: &str) -> Option<uint> {
+        self.program.uniforms.find(&uniform_name.to_string()).map(|&(_, _, size)| size as uint)
+    }
+
+    /// Checks that a vertex buffer, and optionally a list of per-instance buffers, provide all
+    /// the attributes of the program with the right types.
+    ///
+    /// The same check is done at each draw, which fails if the check doesn't pass. This function
+    /// allows you to handle the error instead, for example right after loading the shaders.
+    ///
+    /// Elements of the vertex buffers that are not used by the program are ignored, since the
+    /// GLSL compiler removes the attributes that are not used by the shaders.
+    pub fn check_vertex_buffers<V>(&self, vertex_buffer: &VertexBuffer<V>,
+                                   instances: Option<&InstancesList>)
+        -> Result<(), AttributeError>
+    {
+        let (vertex_buffers, _) = get_vertex_buffers_list(vertex_buffer, instances);
+        vertex_array_object::check_attributes(&*self.program.attributes, &vertex_buffers)
+    }
 }
 
 impl fmt::Show for Program {
@@ -504,7 +1668,38 @@ struct ProgramImpl {
     #[allow(dead_code)]
     shaders: Vec<Arc<ShaderImpl>>,
     id: gl::types::GLuint,
-    uniforms: Arc<HashMap<String, (gl::types::GLint, gl::types::GLenum, gl::types::GLint)>>     // location, type and size of each uniform, ordered by name
+    uniforms: Arc<HashMap<String, (gl::types::GLint, gl::types::GLenum, gl::types::GLint)>>,    // location, type and size of each uniform, ordered by name
+    uniform_blocks: Arc<HashMap<String, UniformBlockReflection>>,
+    attributes: Arc<vertex_array_object::ProgramAttributes>,
+}
+
+impl Drop for ProgramImpl {
+    fn drop(&mut self) {
+        vertex_array_object::purge_program(&self.display, self.id);
+        framebuffer::purge_program(&self.display, self.id);
+
+        let id = self.id.clone();
+        self.display.context.exec(proc(gl) {
+            gl.DeleteProgram(id);
+        });
+    }
+}
+
+/// Informations about a uniform block of a program.
+struct UniformBlockReflection {
+    /// Index of the block in the program, which is also the index of its binding point.
+    index: gl::types::GLuint,
+
+    /// Minimal size in bytes of the buffer bound to this block.
+    size: uint,
+
+    /// Name, type, number of array elements (`None` if the member is not an array) and offset
+    /// of each member, ordered by offset.
+    members: Vec<(String, gl::types::GLenum, Option<uint>, uint)>,
+
+    /// Whether the offsets of the members follow the std140 rules. Uniform buffers can only be
+    /// bound to such blocks.
+    std140: bool,
 }
 
 /// A program which stores values of uniforms.
@@ -512,172 +1707,539 @@ struct ProgramImpl {
 pub struct ProgramUniforms {
     display: Arc<DisplayImpl>,
     program: Arc<ProgramImpl>,
-    textures: HashMap<gl::types::GLint, Arc<texture::TextureImpl>>,
-    values: HashMap<gl::types::GLint, (gl::types::GLenum, Vec<char>)>,
-    uniforms: Arc<HashMap<String, (gl::types::GLint, gl::types::GLenum, gl::types::GLint)>>     // same as the program's variable
+    textures: HashMap<gl::types::GLint, (Arc<texture::TextureImpl>, Option<Arc<sampler::SamplerObject>>)>,     // texture and sampler of each sampler uniform
+    values: Vec<(gl::types::GLint, gl::types::GLenum, gl::types::GLsizei, Vec<char>)>,     // location, type, number of elements and data, in the order they have been set
+    uniform_buffers: HashMap<gl::types::GLuint, Arc<uniform_buffer::UniformBufferImpl>>,     // buffer bound to each block index
+    uniforms: Arc<HashMap<String, (gl::types::GLint, gl::types::GLenum, gl::types::GLint)>>,    // same as the program's variable
+    strict: bool,
 }
 
 impl ProgramUniforms {
     /// Modifies the value of a uniform of the program.
     ///
     /// `uniform_name` must be the name of a uniform in the program.
-    /// Nothing happens if the program doesn't contain a uniform with this name.
+    /// Elements of arrays and members of structs can be accessed with the same syntax as in
+    ///  GLSL, for example `lights[2].color`.
+    /// Nothing happens if the program doesn't contain a uniform with this name, unless strict
+    ///  mode is enabled.
     /// However the function will fail if the type of data doesn't match the type required
     ///  by the shader source code.
     pub fn set_value<T: data_types::UniformValue>(&mut self, uniform_name: &str, value: T) {
-        let &(location, gltype, _) = match self.uniforms.find(&uniform_name.to_string()) {
+        let result = self.try_set_value(uniform_name, value);
+        self.handle_error(result);
+    }
+
+    /// Modifies the value of a uniform of the program.
+    ///
+    /// Same as `set_value`, but returns an error instead of failing or ignoring the value.
+    pub fn try_set_value<T: data_types::UniformValue>(&mut self, uniform_name: &str, value: T)
+        -> Result<(), UniformError>
+    {
+        self.try_set_array(uniform_name, &[value])
+    }
+
+    /// Modifies the value of the first elements of a uniform array.
+    ///
+    /// `uniform_name` must be the name of a uniform in the program, without any `[0]` suffix.
+    /// Nothing happens if the program doesn't contain a uniform with this name, unless strict
+    ///  mode is enabled.
+    /// However the function will fail if the type of data doesn't match the type required
+    ///  by the shader source code, or if there are more values than elements in the array.
+    pub fn set_array<T: data_types::UniformValue>(&mut self, uniform_name: &str, values: &[T]) {
+        let result = self.try_set_array(uniform_name, values);
+        self.handle_error(result);
+    }
+
+    /// Modifies the value of the first elements of a uniform array.
+    ///
+    /// Same as `set_array`, but returns an error instead of failing or ignoring the values.
+    pub fn try_set_array<T: data_types::UniformValue>(&mut self, uniform_name: &str, values: &[T])
+        -> Result<(), UniformError>
+    {
+        let &(location, gltype, size) = match self.uniforms.find(&uniform_name.to_string()) {
             Some(a) => a,
-            None => return      // the uniform is not used, we ignore it
+            None => return Err(UniformNotFound(uniform_name.to_string()))
         };
 
-        if gltype != data_types::UniformValue::get_gl_type(None::<T>) {
-            fail!("Type of data passed to set_value must match the type of data requested by the shader")
+        let value_type = data_types::UniformValue::get_gl_type(None::<T>);
+        if gltype != value_type {
+            return Err(UniformTypeMismatch(uniform_name.to_string(), get_glsl_type_name(gltype),
+                get_glsl_type_name(value_type)));
+        }
+
+        if values.len() > size as uint {
+            return Err(UniformArrayTooSmall(uniform_name.to_string(), size as uint, values.len()));
         }
 
-        let mut data: Vec<char> = Vec::with_capacity(std::mem::size_of_val(&value));
-        unsafe { data.set_len(std::mem::size_of_val(&value)); }
+        let data_size = values.len() * std::mem::size_of::<T>();
+        let mut data: Vec<char> = Vec::with_capacity(data_size);
+        unsafe {
+            data.set_len(data_size);
+            std::ptr::copy_nonoverlapping_memory(data.as_mut_ptr() as *mut T, values.as_ptr(),
+                values.len());
+        }
+
+        // the elements of an array are at consecutive locations, so each value covers the
+        //  range `location .. location + count`
+        let count = values.len() as gl::types::GLsizei;
+
+        // writing inside the range of the last value that overlaps this one (for example an
+        //  element of an array that has been set as a whole) is done in the existing data
+        for entry in self.values.mut_iter().rev() {
+            match *entry {
+                (loc, ty, len, ref mut existing) if ty == gltype && loc <= location &&
+                                                    location + count <= loc + len =>
+                {
+                    let offset = (location - loc) as uint * std::mem::size_of::<T>();
+                    for (dest, src) in existing.mut_slice_from(offset).mut_iter().zip(data.iter()) {
+                        *dest = *src;
+                    }
+
+                    return Ok(());
+                },
+                (loc, _, len, _) if loc < location + count && location < loc + len => break,
+                _ => ()
+            }
+        }
 
-        let data_inside = data.as_mut_ptr() as *mut T;
-        unsafe { (*data_inside) = value; }
+        // values are uploaded in the order they were set, so values that only partially
+        //  overlap this one are kept, and only those that are entirely overwritten are removed
+        self.values.retain(|&(loc, _, len, _)| loc < location || location + count < loc + len);
+        self.values.push((location, gltype, count, data));
 
-        self.values.insert(location.clone(), (gltype, data));
+        Ok(())
     }
 
     /// Modifies the value of a texture uniform of the program.
     ///
     /// `uniform_name` must be the name of a uniform in the program.
-    /// Nothing happens if the program doesn't contain a uniform with this name.
+    /// Nothing happens if the program doesn't contain a uniform with this name, unless strict
+    ///  mode is enabled.
     /// However the function will fail if you call this function for a non-texture uniform.
     pub fn set_texture(&mut self, uniform_name: &str, texture: &Texture) {
+        let result = self.try_set_texture(uniform_name, texture);
+        self.handle_error(result);
+    }
+
+    /// Modifies the value of a texture uniform of the program.
+    ///
+    /// Same as `set_texture`, but returns an error instead of failing or ignoring the texture.
+    pub fn try_set_texture(&mut self, uniform_name: &str, texture: &Texture)
+        -> Result<(), UniformError>
+    {
+        let location = try!(self.get_sampler_location(uniform_name));
+        self.textures.insert(location, (texture::get_impl(texture).clone(), None));
+        Ok(())
+    }
+
+    /// Modifies the value of a texture uniform of the program, and the way the shader
+    ///  samples it.
+    ///
+    /// Contrary to `set_texture`, which uses the wrapping and filtering parameters stored in
+    ///  the texture, the parameters of `behavior` only apply to this uniform.
+    /// Nothing happens if the program doesn't contain a uniform with this name, unless strict
+    ///  mode is enabled.
+    /// However the function will fail if you call this function for a non-texture uniform.
+    pub fn set_texture_with_sampler(&mut self, uniform_name: &str, texture: &Texture,
+                                    behavior: &SamplerBehavior)
+    {
+        let result = self.try_set_texture_with_sampler(uniform_name, texture, behavior);
+        self.handle_error(result);
+    }
+
+    /// Modifies the value of a texture uniform of the program, and the way the shader
+    ///  samples it.
+    ///
+    /// Same as `set_texture_with_sampler`, but returns an error instead of failing or
+    ///  ignoring the texture.
+    pub fn try_set_texture_with_sampler(&mut self, uniform_name: &str, texture: &Texture,
+                                        behavior: &SamplerBehavior)
+        -> Result<(), UniformError>
+    {
+        let location = try!(self.get_sampler_location(uniform_name));
+
+        // reusing the existing sampler object if the behavior didn't change
+        let sampler = match self.textures.find(&location) {
+            Some(&(_, Some(ref s))) if s.behavior == *behavior => s.clone(),
+            _ => Arc::new(sampler::SamplerObject::new(&self.display, behavior))
+        };
+
+        self.textures.insert(location, (texture::get_impl(texture).clone(), Some(sampler)));
+        Ok(())
+    }
+
+    /// Returns the location of a sampler uniform.
+    fn get_sampler_location(&self, uniform_name: &str)
+        -> Result<gl::types::GLint, UniformError>
+    {
         let &(location, gltype, _) = match self.uniforms.find(&uniform_name.to_string()) {
             Some(a) => a,
-            None => return      // the uniform is not used, we ignore it
+            None => return Err(UniformNotFound(uniform_name.to_string()))
         };
 
-        // TODO: fix the check for GLES
-        /*match gltype {
-            gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE |
-            gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_1D_ARRAY |
-            gl::SAMPLER_2D_ARRAY | gl::SAMPLER_1D_ARRAY_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW |
-            gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_2D_MULTISAMPLE_ARRAY |
-            gl::SAMPLER_CUBE_SHADOW | gl::SAMPLER_BUFFER | gl::SAMPLER_2D_RECT |
-            gl::SAMPLER_2D_RECT_SHADOW | gl::INT_SAMPLER_1D | gl::INT_SAMPLER_2D |
-            gl::INT_SAMPLER_3D | gl::INT_SAMPLER_CUBE | gl::INT_SAMPLER_1D_ARRAY |
-            gl::INT_SAMPLER_2D_ARRAY | gl::INT_SAMPLER_2D_MULTISAMPLE |
-            gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::INT_SAMPLER_BUFFER |
-            gl::INT_SAMPLER_2D_RECT | gl::UNSIGNED_INT_SAMPLER_1D | gl::UNSIGNED_INT_SAMPLER_2D |
-            gl::UNSIGNED_INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_CUBE |
-            gl::UNSIGNED_INT_SAMPLER_1D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY |
-            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE |
-            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::UNSIGNED_INT_SAMPLER_BUFFER |
-            gl::UNSIGNED_INT_SAMPLER_2D_RECT
-                => (),
-            _ => fail!("Trying to bind a texture to a non-texture uniform")
-        };*/
-
-        self.textures.insert(location.clone(), texture::get_impl(texture).clone());
-    }
-}
-
-/// Frame buffer.
-struct FrameBufferObject {
-    display: Arc<DisplayImpl>,
-    id: gl::types::GLuint,
-}
+        if !is_sampler_type(gltype) {
+            return Err(UniformNotASampler(uniform_name.to_string(), get_glsl_type_name(gltype)));
+        }
 
-impl FrameBufferObject {
-    /// Builds a new FBO.
-    fn new(display: Arc<DisplayImpl>) -> FrameBufferObject {
-        let id = display.context.exec(proc(gl) {
-            unsafe {
-                let id: gl::types::GLuint = std::mem::uninitialized();
-                gl.GenFramebuffers(1, std::mem::transmute(&id));
-                id
+        Ok(location)
+    }
+
+    /// Binds a uniform buffer to a uniform block of the program.
+    ///
+    /// `block_name` must be the name of a uniform block in the program.
+    /// Nothing happens if the program doesn't contain a block with this name, unless strict
+    ///  mode is enabled.
+    /// However the function will fail if the block doesn't use the `std140` layout, or if the
+    ///  layout of the buffer doesn't match the layout of the block.
+    pub fn set_uniform_buffer<T>(&mut self, block_name: &str, buffer: &UniformBuffer<T>) {
+        let result = self.try_set_uniform_buffer(block_name, buffer);
+        self.handle_error(result);
+    }
+
+    /// Binds a uniform buffer to a uniform block of the program.
+    ///
+    /// Same as `set_uniform_buffer`, but returns an error instead of failing or ignoring
+    ///  the buffer.
+    pub fn try_set_uniform_buffer<T>(&mut self, block_name: &str, buffer: &UniformBuffer<T>)
+        -> Result<(), UniformError>
+    {
+        let block = match self.program.uniform_blocks.find(&block_name.to_string()) {
+            Some(b) => b,
+            None => return Err(UniformNotFound(block_name.to_string()))
+        };
+
+        // the content of uniform buffers is always written with the std140 layout
+        if !block.std140 {
+            return Err(UniformBlockNotStd140(block_name.to_string()));
+        }
+
+        let buffer_layout = uniform_buffer::get_std140_layout(buffer);
+
+        // each member of the block must be in the buffer, with the same type and offset
+        for &(ref name, gltype, array_size, offset) in block.members.iter() {
+            let matches = buffer_layout.iter().any(|&(ref n, ty, a, o)| {
+                n == name && ty == gltype && a == array_size && o == offset
+            });
+
+            if !matches {
+                return Err(UniformBlockLayoutMismatch(block_name.to_string(), name.clone()));
             }
-        }).get();
+        }
 
-        FrameBufferObject {
-            display: display,
-            id: id,
+        let buffer = uniform_buffer::get_impl(buffer);
+        if buffer.size < block.size {
+            return Err(UniformBufferTooSmall(block_name.to_string(), block.size, buffer.size));
         }
+
+        self.uniform_buffers.insert(block.index, buffer.clone());
+        Ok(())
     }
-}
 
-impl Drop for FrameBufferObject {
-    fn drop(&mut self) {
-        let id = self.id.clone();
-        self.display.context.exec(proc(gl) {
-            unsafe { gl.DeleteFramebuffers(1, [ id ].as_ptr()); }
-        });
+    /// Enables or disables strict mode.
+    ///
+    /// In strict mode, `set_value`, `set_array` and `set_texture` fail when the program doesn't
+    ///  contain a uniform with the given name, instead of ignoring the value. This is useful
+    ///  during development to catch typos in uniform names. Note however that the GLSL compiler
+    ///  removes uniforms that are not used by the shaders.
+    ///
+    /// Strict mode is disabled by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Handles the result of one of the `try_set_*` functions.
+    fn handle_error(&self, result: Result<(), UniformError>) {
+        match result {
+            Ok(()) => (),
+            Err(UniformNotFound(_)) if !self.strict => (),      // the uniform is not used, we ignore it
+            Err(err) => fail!("{}", err)
+        }
     }
 }
 
-/// Render buffer.
-struct RenderBuffer {
-    display: Arc<DisplayImpl>,
-    id: gl::types::GLuint,
+/// Error that can happen when setting the value of a uniform.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum UniformError {
+    /// The program doesn't contain any active uniform or uniform block with this name.
+    UniformNotFound(String),
+
+    /// The type of the value doesn't match the type of the uniform in the shader.
+    ///
+    /// Contains the name of the uniform, the type expected by the shader and the type
+    ///  of the value.
+    UniformTypeMismatch(String, String, String),
+
+    /// There are more values than elements in the uniform array.
+    ///
+    /// Contains the name of the uniform, the size of the array and the number of values.
+    UniformArrayTooSmall(String, uint, uint),
+
+    /// A texture has been passed to a uniform which is not a sampler.
+    ///
+    /// Contains the name of the uniform and its type.
+    UniformNotASampler(String, String),
+
+    /// The uniform block doesn't use the `std140` layout, which is the layout of uniform buffers.
+    ///
+    /// Contains the name of the block.
+    UniformBlockNotStd140(String),
+
+    /// The layout of a uniform buffer doesn't match the layout of the uniform block.
+    ///
+    /// Contains the name of the block and the name of the first member that doesn't match.
+    UniformBlockLayoutMismatch(String, String),
+
+    /// The uniform buffer is smaller than the uniform block.
+    ///
+    /// Contains the name of the block, the size of the block and the size of the buffer.
+    UniformBufferTooSmall(String, uint, uint),
 }
 
-impl RenderBuffer {
-    /// Builds a new render buffer.
-    fn new(display: Arc<DisplayImpl>) -> RenderBuffer {
-        let id = display.context.exec(proc(gl) {
-            unsafe {
-                let id: gl::types::GLuint = std::mem::uninitialized();
-                gl.GenRenderbuffers(1, std::mem::transmute(&id));
-                id
-            }
-        }).get();
+impl fmt::Show for UniformError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &UniformNotFound(ref name) =>
+                write!(formatter, "The program doesn't contain any uniform named `{}`", name),
+            &UniformTypeMismatch(ref name, ref expected, ref obtained) =>
+                write!(formatter, "The uniform `{}` is a `{}` but a value of type `{}` was \
+                                   passed", name, expected, obtained),
+            &UniformArrayTooSmall(ref name, size, count) =>
+                write!(formatter, "Trying to set {} elements of the uniform array `{}`, which \
+                                   only has {}", count, name, size),
+            &UniformNotASampler(ref name, ref gltype) =>
+                write!(formatter, "Trying to bind a texture to the uniform `{}` of type `{}`, \
+                                   which is not a sampler", name, gltype),
+            &UniformBlockNotStd140(ref block) =>
+                write!(formatter, "The uniform block `{}` must use the std140 layout", block),
+            &UniformBlockLayoutMismatch(ref block, ref member) =>
+                write!(formatter, "The layout of the buffer doesn't match the member `{}` of \
+                                   the uniform block `{}`", member, block),
+            &UniformBufferTooSmall(ref block, block_size, buffer_size) =>
+                write!(formatter, "The uniform block `{}` requires {} bytes but the buffer \
+                                   only has {}", block, block_size, buffer_size),
+        }
+    }
+}
 
-        RenderBuffer {
-            display: display,
-            id: id,
+/// Error that can happen when the vertex buffers of a draw don't match the attributes of
+/// the program.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    /// The program has an active attribute that none of the vertex buffers provide.
+    AttributeMissing(String),
+
+    /// The type of an element of a vertex buffer doesn't match the type of the attribute in
+    /// the shader.
+    ///
+    /// Contains the name of the attribute, the type expected by the shader and the type
+    ///  of the element.
+    AttributeTypeMismatch(String, String, String),
+}
+
+impl fmt::Show for AttributeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &AttributeMissing(ref name) =>
+                write!(formatter, "The program requires an attribute named `{}` but the vertex \
+                                   buffers don't provide it", name),
+            &AttributeTypeMismatch(ref name, ref expected, ref obtained) =>
+                write!(formatter, "The attribute `{}` is a `{}` but the vertex buffer provides \
+                                   a `{}`", name, expected, obtained),
         }
     }
 }
 
-impl Drop for RenderBuffer {
-    fn drop(&mut self) {
-        let id = self.id.clone();
-        self.display.context.exec(proc(gl) {
-            unsafe { gl.DeleteRenderbuffers(1, [ id ].as_ptr()); }
-        });
+/// Returns the name of a GLSL type, for error messages.
+fn get_glsl_type_name(gltype: gl::types::GLenum) -> String {
+    match gltype {
+        gl::FLOAT => "float".to_string(),
+        gl::FLOAT_VEC2 => "vec2".to_string(),
+        gl::FLOAT_VEC3 => "vec3".to_string(),
+        gl::FLOAT_VEC4 => "vec4".to_string(),
+        gl::INT => "int".to_string(),
+        gl::INT_VEC2 => "ivec2".to_string(),
+        gl::INT_VEC3 => "ivec3".to_string(),
+        gl::INT_VEC4 => "ivec4".to_string(),
+        gl::BOOL => "bool".to_string(),
+        gl::BOOL_VEC2 => "bvec2".to_string(),
+        gl::BOOL_VEC3 => "bvec3".to_string(),
+        gl::BOOL_VEC4 => "bvec4".to_string(),
+        gl::FLOAT_MAT2 => "mat2".to_string(),
+        gl::FLOAT_MAT3 => "mat3".to_string(),
+        gl::FLOAT_MAT4 => "mat4".to_string(),
+        gl::SAMPLER_2D => "sampler2D".to_string(),
+        gl::SAMPLER_CUBE => "samplerCube".to_string(),
+        _ => get_desktop_glsl_type_name(gltype)
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_desktop_glsl_type_name(gltype: gl::types::GLenum) -> String {
+    match gltype {
+        gl::UNSIGNED_INT => "uint".to_string(),
+        gl::UNSIGNED_INT_VEC2 => "uvec2".to_string(),
+        gl::UNSIGNED_INT_VEC3 => "uvec3".to_string(),
+        gl::UNSIGNED_INT_VEC4 => "uvec4".to_string(),
+        gl::DOUBLE => "double".to_string(),
+        gl::DOUBLE_VEC2 => "dvec2".to_string(),
+        gl::DOUBLE_VEC3 => "dvec3".to_string(),
+        gl::DOUBLE_VEC4 => "dvec4".to_string(),
+        gl::FLOAT_MAT2x3 => "mat2x3".to_string(),
+        gl::FLOAT_MAT2x4 => "mat2x4".to_string(),
+        gl::FLOAT_MAT3x2 => "mat3x2".to_string(),
+        gl::FLOAT_MAT3x4 => "mat3x4".to_string(),
+        gl::FLOAT_MAT4x2 => "mat4x2".to_string(),
+        gl::FLOAT_MAT4x3 => "mat4x3".to_string(),
+        gl::SAMPLER_1D => "sampler1D".to_string(),
+        gl::SAMPLER_3D => "sampler3D".to_string(),
+        gl::SAMPLER_1D_ARRAY => "sampler1DArray".to_string(),
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray".to_string(),
+        _ => format!("<unknown type 0x{:x}>", gltype)
+    }
+}
+
+#[cfg(target_os = "android")]
+fn get_desktop_glsl_type_name(gltype: gl::types::GLenum) -> String {
+    format!("<unknown type 0x{:x}>", gltype)
+}
+
+/// Returns true if the type of uniform is a sampler.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn is_sampler_type(gltype: gl::types::GLenum) -> bool {
+    match gltype {
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE |
+        gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_1D_ARRAY |
+        gl::SAMPLER_2D_ARRAY | gl::SAMPLER_1D_ARRAY_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW |
+        gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_2D_MULTISAMPLE_ARRAY |
+        gl::SAMPLER_CUBE_SHADOW | gl::SAMPLER_BUFFER | gl::SAMPLER_2D_RECT |
+        gl::SAMPLER_2D_RECT_SHADOW | gl::INT_SAMPLER_1D | gl::INT_SAMPLER_2D |
+        gl::INT_SAMPLER_3D | gl::INT_SAMPLER_CUBE | gl::INT_SAMPLER_1D_ARRAY |
+        gl::INT_SAMPLER_2D_ARRAY | gl::INT_SAMPLER_2D_MULTISAMPLE |
+        gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::INT_SAMPLER_BUFFER |
+        gl::INT_SAMPLER_2D_RECT | gl::UNSIGNED_INT_SAMPLER_1D | gl::UNSIGNED_INT_SAMPLER_2D |
+        gl::UNSIGNED_INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_CUBE |
+        gl::UNSIGNED_INT_SAMPLER_1D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY |
+        gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE |
+        gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::UNSIGNED_INT_SAMPLER_BUFFER |
+        gl::UNSIGNED_INT_SAMPLER_2D_RECT
+            => true,
+        _ => false
+    }
+}
+
+/// Returns true if the type of uniform is a sampler.
+#[cfg(target_os = "android")]
+fn is_sampler_type(gltype: gl::types::GLenum) -> bool {
+    match gltype {
+        gl::SAMPLER_2D | gl::SAMPLER_CUBE => true,
+        _ => false
+    }
+}
+
+/// Error that can happen when building a `Display`.
+#[deriving(Clone, PartialEq, Eq)]
+pub enum DisplayCreationError {
+    /// The window or the OpenGL context could not be created. Contains the reason given by
+    ///  the windowing library.
+    ContextCreationFailed(String),
+
+    /// The default framebuffer of this kind of context can't have a depth or a stencil buffer.
+    ///  Contains the requested number of depth bits and stencil bits.
+    DepthStencilNotSupported(u8, u8),
+
+    /// The default framebuffer of this kind of context can't be multisampled. Contains the
+    ///  requested number of samples.
+    MultisamplingNotSupported(u16),
+}
+
+impl fmt::Show for DisplayCreationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        match self {
+            &ContextCreationFailed(ref reason) =>
+                write!(formatter, "Failed to create the context: {}", reason),
+            &DepthStencilNotSupported(depth_bits, stencil_bits) =>
+                write!(formatter, "A default framebuffer with {} depth bits and {} stencil bits \
+                                   is not supported by this kind of context", depth_bits,
+                                   stencil_bits),
+            &MultisamplingNotSupported(samples) =>
+                write!(formatter, "A default framebuffer with {} samples is not supported by \
+                                   this kind of context", samples),
+        }
     }
 }
 
 /// Objects that can build a `Display` object.
 pub trait DisplayBuild {
     /// Build a context and a `Display` to draw on it.
-    fn build_simple_gl(self) -> Result<Display, ()>;
+    fn build_simple_gl(self) -> Result<Display, DisplayCreationError>;
+
+    /// Build a context whose default framebuffer has a depth buffer and a stencil buffer
+    /// with the given number of bits, and a `Display` to draw on it.
+    ///
+    /// Passing `0` means that you don't need the corresponding buffer.
+    fn build_simple_gl_with_depth_stencil(self, depth_bits: u8, stencil_bits: u8)
+        -> Result<Display, DisplayCreationError>;
+
+    /// Build a context whose default framebuffer is multisampled with the given number of
+    /// samples, and a `Display` to draw on it.
+    ///
+    /// Drawing on the display is then antialiased.
+    fn build_simple_gl_with_multisampling(self, samples: u16)
+        -> Result<Display, DisplayCreationError>;
 }
 
 impl DisplayBuild for glutin::WindowBuilder {
-    fn build_simple_gl(self) -> Result<Display, ()> {
-        let window = try!(self.build().map_err(|_| ()));
-        let context = context::Context::new(window);
+    fn build_simple_gl(self) -> Result<Display, DisplayCreationError> {
+        let window = try!(self.build().map_err(|e| ContextCreationFailed(format!("{}", e))));
+        let context = context::Context::new_from_window(window);
+        Ok(Display::from_context(context))
+    }
 
-        let gl_version = context.exec(proc(gl) {
-            // TODO: not supported by GLES
-            (0, 0)
-            /*unsafe {
-                use std::mem;
+    fn build_simple_gl_with_depth_stencil(self, depth_bits: u8, stencil_bits: u8)
+        -> Result<Display, DisplayCreationError>
+    {
+        self.with_depth_buffer(depth_bits).with_stencil_buffer(stencil_bits).build_simple_gl()
+    }
 
-                let mut major_version: gl::types::GLint = mem::uninitialized();
-                let mut minor_version: gl::types::GLint = mem::uninitialized();
+    fn build_simple_gl_with_multisampling(self, samples: u16)
+        -> Result<Display, DisplayCreationError>
+    {
+        self.with_multisampling(samples).build_simple_gl()
+    }
+}
 
-                gl.GetIntegerv(gl::MAJOR_VERSION, &mut major_version);
-                gl.GetIntegerv(gl::MINOR_VERSION, &mut minor_version);
+#[cfg(feature = "headless")]
+impl DisplayBuild for glutin::HeadlessRendererBuilder {
+    fn build_simple_gl(self) -> Result<Display, DisplayCreationError> {
+        self.build_simple_gl_with_depth_stencil(0, 0)
+    }
 
-                (major_version, minor_version)
-            }*/
-        }).get();
+    /// Headless contexts don't have a depth or a stencil buffer, so the display draws on
+    /// render buffers with the requested number of bits instead. Returns
+    /// `DepthStencilNotSupported` if this number of bits is not supported.
+    fn build_simple_gl_with_depth_stencil(self, depth_bits: u8, stencil_bits: u8)
+        -> Result<Display, DisplayCreationError>
+    {
+        let window = try!(self.build().map_err(|e| ContextCreationFailed(format!("{}", e))));
+        let context = try!(context::Context::new_from_headless(window, depth_bits,
+            stencil_bits));
+        Ok(Display::from_context(context))
+    }
 
-        Ok(Display {
-            context: Arc::new(DisplayImpl {
-                context: context,
-                gl_version: gl_version,
-            }),
-        })
+    /// Headless contexts can't be multisampled. Returns `MultisamplingNotSupported` if
+    /// `samples` is not `0`; draw on a multisampled texture and resolve it instead.
+    fn build_simple_gl_with_multisampling(self, samples: u16)
+        -> Result<Display, DisplayCreationError>
+    {
+        if samples != 0 {
+            return Err(MultisamplingNotSupported(samples));
+        }
+
+        self.build_simple_gl()
     }
 }
 
@@ -689,27 +2251,110 @@ pub struct Display {
 struct DisplayImpl {
     context: context::Context,
     gl_version: (gl::types::GLint, gl::types::GLint),
+
+    // maximum level of anisotropic filtering, or `None` if it is not supported
+    max_anisotropy: Option<f32>,
+
+    // vertex array objects of each combination of program and buffers that has been drawn
+    vertex_array_objects: Mutex<HashMap<vertex_array_object::VertexArrayObjectKey, gl::types::GLuint>>,
+
+    // draw buffers of each combination of program and framebuffer with named outputs
+    draw_buffers: Mutex<HashMap<(gl::types::GLuint, gl::types::GLuint), Vec<gl::types::GLenum>>>,
 }
 
 impl Display {
+    /// Builds the `Display` object around a context.
+    fn from_context(context: context::Context) -> Display {
+        let gl_version = context.exec(proc(gl) {
+            // `GL_MAJOR_VERSION` and `GL_MINOR_VERSION` don't exist before OpenGL 3 and in GLES,
+            //  so we parse the version string instead
+            let version = unsafe {
+                std::c_str::CString::new(gl.GetString(gl::VERSION) as *const libc::c_char, false)
+            };
+
+            // GLES versions are prefixed with "OpenGL ES "
+            let version = version.as_str().unwrap_or("").trim_left_chars(|c: char| !c.is_digit());
+            let mut numbers = version.split(|c: char| !c.is_digit())
+                .map(|n| from_str::<gl::types::GLint>(n).unwrap_or(0));
+
+            let major = numbers.next().unwrap_or(0);
+            let minor = numbers.next().unwrap_or(0);
+            (major, minor)
+        }).get();
+
+        let max_anisotropy = context.exec(proc(gl) {
+            sampler::get_max_supported_anisotropy(gl)
+        }).get();
+
+        Display {
+            context: Arc::new(DisplayImpl {
+                context: context,
+                gl_version: gl_version,
+                max_anisotropy: max_anisotropy,
+                vertex_array_objects: Mutex::new(HashMap::new()),
+                draw_buffers: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
     /// Reads all events received by the window.
     pub fn poll_events(&self) -> Vec<glutin::Event> {
         self.context.context.recv()
     }
 
-    /// 
+    /// Reads the content of the last frame that has been finished.
+    ///
+    /// Returns the RGBA pixels of the default framebuffer, row by row, starting with the top row.
+    /// The result is empty if no frame has been finished yet.
+    ///
+    /// The back buffer is copied at the end of each frame, just before the buffers are swapped,
+    /// so this function doesn't wait for the frame currently being drawn.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// let target = display.draw();
+    /// // ...
+    /// target.finish();
+    ///
+    /// let pixels = display.screenshot();
+    /// ```
+    pub fn screenshot(&self) -> Vec<Vec<(u8, u8, u8, u8)>> {
+        self.context.context.screenshot()
+    }
+
+    /// Returns the width and height of the default framebuffer, in pixels.
+    pub fn get_framebuffer_dimensions(&self) -> (uint, uint) {
+        self.context.context.get_framebuffer_dimensions()
+    }
+
+    /// Start drawing on the window.
+    ///
+    /// The frame is shown when the target is dropped, then the window is cleared to opaque
+    /// black. Use `Target::clear_color` to start a frame with another color.
     pub fn draw(&self) -> Target {
         Target {
             display: self.context.clone(),
             display_hold: Some(self),
-            texture: None,
+            textures: Vec::new(),
+            dimensions: self.get_framebuffer_dimensions(),
+            viewport: None,
+            scissor: None,
             framebuffer: None,
             execute_end: Some(proc(context: &DisplayImpl) {
                 context.context.swap_buffers();
 
+                let framebuffer = context.context.get_default_framebuffer();
                 context.context.exec(proc(gl) {
+                    gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+                    gl.Disable(gl::SCISSOR_TEST);
                     gl.ClearColor(0.0, 0.0, 0.0, 1.0);
-                    gl.Clear(gl::COLOR_BUFFER_BIT);
+                    gl.DepthMask(gl::TRUE);
+                    clear_depth_value(gl, 1.0);
+                    gl.StencilMask(0xffffffff);
+                    gl.ClearStencil(0);
+                    gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
                 });
             }),
         }
@@ -855,7 +2500,7 @@ impl Display {
             }
         }).get());
 
-        let uniforms = self.context.context.exec(proc(gl) {
+        let (uniforms, uniform_blocks, attributes) = self.context.context.exec(proc(gl) {
             unsafe {
                 // reflecting program uniforms
                 let mut uniforms = HashMap::new();
@@ -863,33 +2508,217 @@ impl Display {
                 let mut active_uniforms: gl::types::GLint = std::mem::uninitialized();
                 gl.GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut active_uniforms);
 
+                let mut max_name_length: gl::types::GLint = std::mem::uninitialized();
+                gl.GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_length);
+
                 for uniform_id in range(0, active_uniforms) {
-                    let mut uniform_name_tmp: Vec<u8> = Vec::with_capacity(64);
-                    let mut uniform_name_tmp_len = 63;
+                    let mut uniform_name_tmp: Vec<u8> = Vec::with_capacity(max_name_length as uint);
+                    let mut uniform_name_tmp_len = max_name_length;
 
                     let mut data_type: gl::types::GLenum = std::mem::uninitialized();
                     let mut data_size: gl::types::GLint = std::mem::uninitialized();
                     gl.GetActiveUniform(id, uniform_id as gl::types::GLuint, uniform_name_tmp_len, &mut uniform_name_tmp_len, &mut data_size, &mut data_type, uniform_name_tmp.as_mut_slice().as_mut_ptr() as *mut gl::types::GLchar);
                     uniform_name_tmp.set_len(uniform_name_tmp_len as uint);
 
+                    // members of uniform blocks are reflected separately
+                    if get_uniform_block_index(gl, id, uniform_id as gl::types::GLuint) != -1 {
+                        continue;
+                    }
+
                     let uniform_name = String::from_utf8(uniform_name_tmp).unwrap();
                     let location = gl.GetUniformLocation(id, uniform_name.to_c_str().unwrap());
 
-                    uniforms.insert(uniform_name, (location, data_type, data_size));
+                    // arrays are reported with a `[0]` suffix ; we register the array under its
+                    //  name without the suffix, and each of its elements individually
+                    if uniform_name.as_slice().ends_with("[0]") {
+                        let array_name = uniform_name.as_slice()
+                            .slice_to(uniform_name.len() - 3).to_string();
+
+                        for element in range(0, data_size) {
+                            let element_name = format!("{}[{}]", array_name, element);
+                            let element_location = gl.GetUniformLocation(id,
+                                element_name.to_c_str().unwrap());
+                            uniforms.insert(element_name, (element_location, data_type, 1));
+                        }
+
+                        uniforms.insert(array_name, (location, data_type, data_size));
+
+                    } else {
+                        uniforms.insert(uniform_name, (location, data_type, data_size));
+                    }
                 }
 
-                Arc::new(uniforms)
+                (Arc::new(uniforms), reflect_uniform_blocks(gl, id), reflect_attributes(gl, id))
             }
         }).get();
 
-
         Ok(Program {
             program: Arc::new(ProgramImpl {
                 display: self.context.clone(),
                 shaders: shaders_store,
                 id: id,
-                uniforms: uniforms
+                uniforms: uniforms,
+                uniform_blocks: Arc::new(uniform_blocks),
+                attributes: Arc::new(attributes),
             })
         })
     }
 }
+
+/// Reflects the vertex attributes of a program.
+unsafe fn reflect_attributes(gl: &gl::Gl, program: gl::types::GLuint)
+                             -> vertex_array_object::ProgramAttributes
+{
+    let mut attributes = HashMap::new();
+
+    let mut active_attributes: gl::types::GLint = std::mem::uninitialized();
+    gl.GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut active_attributes);
+
+    let mut max_name_length: gl::types::GLint = std::mem::uninitialized();
+    gl.GetProgramiv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_name_length);
+
+    for attribute_id in range(0, active_attributes) {
+        let mut attribute_name_tmp: Vec<u8> = Vec::with_capacity(max_name_length as uint);
+        let mut attribute_name_tmp_len = max_name_length;
+
+        let mut data_type: gl::types::GLenum = std::mem::uninitialized();
+        let mut data_size: gl::types::GLint = std::mem::uninitialized();
+        gl.GetActiveAttrib(program, attribute_id as gl::types::GLuint, attribute_name_tmp_len, &mut attribute_name_tmp_len, &mut data_size, &mut data_type, attribute_name_tmp.as_mut_slice().as_mut_ptr() as *mut gl::types::GLchar);
+        attribute_name_tmp.set_len(attribute_name_tmp_len as uint);
+
+        let attribute_name = String::from_utf8(attribute_name_tmp).unwrap();
+
+        // built-in attributes like `gl_VertexID` don't have a location
+        let location = gl.GetAttribLocation(program, attribute_name.to_c_str().unwrap());
+        if location == -1 {
+            continue;
+        }
+
+        attributes.insert(attribute_name, (location, data_type, data_size));
+    }
+
+    attributes
+}
+
+/// Returns the index of the uniform block that contains a uniform, or -1 if the uniform is
+/// not in a block.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+unsafe fn get_uniform_block_index(gl: &gl::Gl, program: gl::types::GLuint,
+                                  uniform: gl::types::GLuint) -> gl::types::GLint
+{
+    let mut block_index: gl::types::GLint = std::mem::uninitialized();
+    gl.GetActiveUniformsiv(program, 1, &uniform, gl::UNIFORM_BLOCK_INDEX, &mut block_index);
+    block_index
+}
+
+/// Returns the index of the uniform block that contains a uniform, or -1 if the uniform is
+/// not in a block.
+#[cfg(target_os = "android")]
+unsafe fn get_uniform_block_index(_: &gl::Gl, _: gl::types::GLuint, _: gl::types::GLuint)
+                                  -> gl::types::GLint
+{
+    -1
+}
+
+/// Reflects the uniform blocks of a program.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+unsafe fn reflect_uniform_blocks(gl: &gl::Gl, program: gl::types::GLuint)
+                                 -> HashMap<String, UniformBlockReflection>
+{
+    let mut blocks = HashMap::new();
+
+    let mut active_blocks: gl::types::GLint = std::mem::uninitialized();
+    gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut active_blocks);
+
+    let mut max_block_name_length: gl::types::GLint = std::mem::uninitialized();
+    gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH, &mut max_block_name_length);
+
+    let mut max_name_length: gl::types::GLint = std::mem::uninitialized();
+    gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_length);
+
+    for block_id in range(0, active_blocks as gl::types::GLuint) {
+        let mut block_name_tmp: Vec<u8> = Vec::with_capacity(max_block_name_length as uint);
+        let mut block_name_tmp_len = max_block_name_length;
+        gl.GetActiveUniformBlockName(program, block_id, block_name_tmp_len, &mut block_name_tmp_len, block_name_tmp.as_mut_slice().as_mut_ptr() as *mut gl::types::GLchar);
+        block_name_tmp.set_len(block_name_tmp_len as uint);
+        let block_name = String::from_utf8(block_name_tmp).unwrap();
+
+        let mut data_size: gl::types::GLint = std::mem::uninitialized();
+        gl.GetActiveUniformBlockiv(program, block_id, gl::UNIFORM_BLOCK_DATA_SIZE, &mut data_size);
+
+        let mut active_members: gl::types::GLint = std::mem::uninitialized();
+        gl.GetActiveUniformBlockiv(program, block_id, gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS, &mut active_members);
+
+        let mut members_indices: Vec<gl::types::GLint> = Vec::from_elem(active_members as uint, 0);
+        gl.GetActiveUniformBlockiv(program, block_id, gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES, members_indices.as_mut_ptr());
+
+        let mut members = Vec::new();
+        for &member_id in members_indices.iter() {
+            let member_id = member_id as gl::types::GLuint;
+
+            let mut member_name_tmp: Vec<u8> = Vec::with_capacity(max_name_length as uint);
+            let mut member_name_tmp_len = max_name_length;
+
+            let mut data_type: gl::types::GLenum = std::mem::uninitialized();
+            let mut array_size: gl::types::GLint = std::mem::uninitialized();
+            gl.GetActiveUniform(program, member_id, member_name_tmp_len, &mut member_name_tmp_len, &mut array_size, &mut data_type, member_name_tmp.as_mut_slice().as_mut_ptr() as *mut gl::types::GLchar);
+            member_name_tmp.set_len(member_name_tmp_len as uint);
+
+            let mut offset: gl::types::GLint = std::mem::uninitialized();
+            gl.GetActiveUniformsiv(program, 1, &member_id, gl::UNIFORM_OFFSET, &mut offset);
+
+            // members are reported as `Block.member` if the block has an instance name, and
+            //  arrays have a `[0]` suffix
+            let member_name = String::from_utf8(member_name_tmp).unwrap();
+            let mut member_name = member_name.as_slice();
+            let prefix = format!("{}.", block_name);
+            if member_name.starts_with(prefix.as_slice()) {
+                member_name = member_name.slice_from(prefix.len());
+            }
+            // an array of one element has the layout of an array, not of a single value
+            let array_size = if member_name.ends_with("[0]") {
+                member_name = member_name.slice_to(member_name.len() - 3);
+                Some(array_size as uint)
+            } else {
+                None
+            };
+
+            members.push((member_name.to_string(), data_type, array_size, offset as uint));
+        }
+
+        members.sort_by(|&(_, _, _, a), &(_, _, _, b)| a.cmp(&b));
+
+        // each block gets the binding point of the same index, so that the binding points
+        //  of the program never have to be changed when drawing
+        gl.UniformBlockBinding(program, block_id, block_id);
+
+        // members of nested structs are not checked
+        let std140 = members.iter().any(|&(ref n, _, _, _)| n.as_slice().contains(".")) || {
+            let (offsets, _) = uniform_buffer::compute_std140_layout(
+                members.iter().map(|&(_, ty, array_size, _)| (ty, array_size)));
+            members.iter().zip(offsets.iter()).all(|(&(_, _, _, o), &expected)| o == expected)
+        };
+
+        blocks.insert(block_name, UniformBlockReflection {
+            index: block_id,
+            size: data_size as uint,
+            members: members,
+            std140: std140,
+        });
+    }
+
+    blocks
+}
+
+/// Reflects the uniform blocks of a program.
+#[cfg(target_os = "android")]
+unsafe fn reflect_uniform_blocks(_: &gl::Gl, _: gl::types::GLuint)
+                                 -> HashMap<String, UniformBlockReflection>
+{
+    // uniform blocks don't exist in GLES 2
+    HashMap::new()
+}
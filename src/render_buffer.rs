@@ -0,0 +1,223 @@
+use gl;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+/// A buffer in the graphics card's memory that can be attached to a texture target, but that
+/// can't be sampled.
+///
+/// Render buffers are used as depth or stencil buffers when drawing on a texture.
+pub struct RenderBuffer {
+    buffer: Arc<RenderBufferImpl>
+}
+
+/// This public function is accessible from within `simple_gl` but not for the user.
+pub fn get_impl<'a>(buffer: &'a RenderBuffer) -> &'a Arc<RenderBufferImpl> {
+    &buffer.buffer
+}
+
+impl RenderBuffer {
+    /// Builds a new render buffer that can be used as a depth buffer.
+    pub fn new_depth(display: &super::Display, width: uint, height: uint) -> RenderBuffer {
+        RenderBuffer::new(display, get_depth_format(), gl::DEPTH_ATTACHMENT, width, height, 0)
+    }
+
+    /// Builds a new render buffer that can be used as a stencil buffer.
+    pub fn new_stencil(display: &super::Display, width: uint, height: uint) -> RenderBuffer {
+        RenderBuffer::new(display, gl::STENCIL_INDEX8, gl::STENCIL_ATTACHMENT, width, height, 0)
+    }
+
+    /// Builds a new render buffer that can be used both as a depth and a stencil buffer.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new_depth_stencil(display: &super::Display, width: uint, height: uint)
+        -> RenderBuffer
+    {
+        RenderBuffer::new(display, gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL_ATTACHMENT, width,
+            height, 0)
+    }
+
+    /// Builds a new render buffer that can be used both as a depth and a stencil buffer.
+    #[cfg(target_os = "android")]
+    pub fn new_depth_stencil(_: &super::Display, _: uint, _: uint) -> RenderBuffer {
+        fail!("Depth-stencil render buffers are not supported by GLES")
+    }
+
+    /// Builds a new multisampled render buffer that can be used as a depth buffer.
+    ///
+    /// It must be attached along with color textures that have the same number of samples.
+    /// Returns an error if the number of samples is not supported.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new_depth_multisample(display: &super::Display, width: uint, height: uint,
+                                 samples: uint)
+        -> Result<RenderBuffer, super::TextureCreationError>
+    {
+        RenderBuffer::new_multisample(display, get_depth_format(), gl::DEPTH_ATTACHMENT, width,
+            height, samples)
+    }
+
+    /// Builds a new multisampled render buffer that can be used as a depth buffer.
+    #[cfg(target_os = "android")]
+    pub fn new_depth_multisample(_: &super::Display, _: uint, _: uint, _: uint)
+        -> Result<RenderBuffer, super::TextureCreationError>
+    {
+        fail!("Multisampled render buffers are not supported by GLES")
+    }
+
+    /// Builds a new multisampled render buffer that can be used both as a depth and a
+    /// stencil buffer.
+    ///
+    /// It must be attached along with color textures that have the same number of samples.
+    /// Returns an error if the number of samples is not supported.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new_depth_stencil_multisample(display: &super::Display, width: uint, height: uint,
+                                         samples: uint)
+        -> Result<RenderBuffer, super::TextureCreationError>
+    {
+        RenderBuffer::new_multisample(display, gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL_ATTACHMENT,
+            width, height, samples)
+    }
+
+    /// Builds a new multisampled render buffer that can be used both as a depth and a
+    /// stencil buffer.
+    #[cfg(target_os = "android")]
+    pub fn new_depth_stencil_multisample(_: &super::Display, _: uint, _: uint, _: uint)
+        -> Result<RenderBuffer, super::TextureCreationError>
+    {
+        fail!("Multisampled render buffers are not supported by GLES")
+    }
+
+    /// Builds a multisampled render buffer, or returns an error if the number of samples is
+    /// `0` or greater than `GL_MAX_SAMPLES`.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    fn new_multisample(display: &super::Display, format: gl::types::GLenum,
+                       attachment: gl::types::GLenum, width: uint, height: uint, samples: uint)
+        -> Result<RenderBuffer, super::TextureCreationError>
+    {
+        let max_samples = display.context.context.exec(proc(gl) {
+            unsafe {
+                let mut max_samples: gl::types::GLint = mem::uninitialized();
+                gl.GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+                max_samples as uint
+            }
+        }).get();
+
+        if samples == 0 || samples > max_samples {
+            return Err(super::SamplesCountNotSupported(samples));
+        }
+
+        Ok(RenderBuffer::new(display, format, attachment, width, height, samples))
+    }
+
+    /// Builds a render buffer. A number of samples of `0` means that the buffer is not
+    /// multisampled.
+    fn new(display: &super::Display, format: gl::types::GLenum, attachment: gl::types::GLenum,
+           width: uint, height: uint, samples: uint) -> RenderBuffer
+    {
+        let id = display.context.context.exec(proc(gl) {
+            unsafe {
+                let id: gl::types::GLuint = mem::uninitialized();
+                gl.GenRenderbuffers(1, mem::transmute(&id));
+                gl.BindRenderbuffer(gl::RENDERBUFFER, id);
+                renderbuffer_storage(gl, format, width, height, samples);
+                id
+            }
+        }).get();
+
+        RenderBuffer {
+            buffer: Arc::new(RenderBufferImpl {
+                display: display.context.clone(),
+                id: id,
+                attachment: attachment,
+                width: width,
+                height: height,
+                samples: samples,
+            })
+        }
+    }
+
+    /// Returns the width of the render buffer.
+    pub fn get_width(&self) -> uint {
+        self.buffer.width
+    }
+
+    /// Returns the height of the render buffer.
+    pub fn get_height(&self) -> uint {
+        self.buffer.height
+    }
+
+    /// Returns the number of samples of the render buffer, or `0` if it is not multisampled.
+    pub fn get_samples(&self) -> uint {
+        self.buffer.samples
+    }
+}
+
+impl fmt::Show for RenderBuffer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        (format!("RenderBuffer #{} (dimensions: {}x{})", self.buffer.id,
+            self.buffer.width, self.buffer.height)).fmt(formatter)
+    }
+}
+
+pub struct RenderBufferImpl {
+    display: Arc<super::DisplayImpl>,
+    pub id: gl::types::GLuint,
+    /// The point where the buffer must be attached in a framebuffer.
+    pub attachment: gl::types::GLenum,
+    pub width: uint,
+    pub height: uint,
+    pub samples: uint,
+}
+
+impl Drop for RenderBufferImpl {
+    fn drop(&mut self) {
+        let id = self.id.clone();
+        self.display.context.exec(proc(gl) {
+            unsafe { gl.DeleteRenderbuffers(1, [ id ].as_ptr()); }
+        });
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_depth_format() -> gl::types::GLenum {
+    gl::DEPTH_COMPONENT24
+}
+
+#[cfg(target_os = "android")]
+fn get_depth_format() -> gl::types::GLenum {
+    gl::DEPTH_COMPONENT16
+}
+
+/// Allocates the storage of the render buffer currently bound.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn renderbuffer_storage(gl: &gl::Gl, format: gl::types::GLenum, width: uint, height: uint,
+                        samples: uint)
+{
+    if samples == 0 {
+        gl.RenderbufferStorage(gl::RENDERBUFFER, format, width as gl::types::GLsizei,
+            height as gl::types::GLsizei);
+    } else {
+        gl.RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as gl::types::GLsizei,
+            format, width as gl::types::GLsizei, height as gl::types::GLsizei);
+    }
+}
+
+/// Allocates the storage of the render buffer currently bound.
+///
+/// GLES doesn't have multisampled render buffers, so `samples` is always `0`.
+#[cfg(target_os = "android")]
+fn renderbuffer_storage(gl: &gl::Gl, format: gl::types::GLenum, width: uint, height: uint, _: uint) {
+    gl.RenderbufferStorage(gl::RENDERBUFFER, format, width as gl::types::GLsizei,
+        height as gl::types::GLsizei);
+}
@@ -0,0 +1,216 @@
+use gl;
+use libc;
+use std::c_str::CString;
+use std::mem;
+use std::sync::Arc;
+
+/// Constants of the `GL_EXT_texture_filter_anisotropic` extension, which are not part of
+///  the core bindings.
+static TEXTURE_MAX_ANISOTROPY_EXT: gl::types::GLenum = 0x84FE;
+static MAX_TEXTURE_MAX_ANISOTROPY_EXT: gl::types::GLenum = 0x84FF;
+
+/// A sampler object, which holds the parameters of a `SamplerBehavior`.
+///
+/// GLES doesn't have sampler objects, so the parameters are applied to the texture itself
+///  when it is bound.
+pub struct SamplerObject {
+    display: Arc<super::DisplayImpl>,
+    pub id: gl::types::GLuint,
+    pub behavior: super::SamplerBehavior,
+}
+
+impl SamplerObject {
+    /// Builds a new sampler object.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new(display: &Arc<super::DisplayImpl>, behavior: &super::SamplerBehavior)
+        -> SamplerObject
+    {
+        let behavior_clone = behavior.clone();
+        let max_anisotropy = get_max_anisotropy(display.max_anisotropy, behavior.max_anisotropy);
+
+        let id = display.context.exec(proc(gl) {
+            unsafe {
+                let behavior = behavior_clone;
+
+                let mut id: gl::types::GLuint = mem::uninitialized();
+                gl.GenSamplers(1, &mut id);
+
+                let (wrap_s, wrap_t, wrap_r) = behavior.wrap_function;
+                gl.SamplerParameteri(id, gl::TEXTURE_WRAP_S, wrap_s.get_gl_enum() as gl::types::GLint);
+                gl.SamplerParameteri(id, gl::TEXTURE_WRAP_T, wrap_t.get_gl_enum() as gl::types::GLint);
+                gl.SamplerParameteri(id, gl::TEXTURE_WRAP_R, wrap_r.get_gl_enum() as gl::types::GLint);
+
+                gl.SamplerParameteri(id, gl::TEXTURE_MIN_FILTER,
+                    behavior.get_minify_filter() as gl::types::GLint);
+                gl.SamplerParameteri(id, gl::TEXTURE_MAG_FILTER,
+                    behavior.magnify_filter.get_gl_enum() as gl::types::GLint);
+
+                match max_anisotropy {
+                    Some(value) => gl.SamplerParameterf(id, TEXTURE_MAX_ANISOTROPY_EXT, value),
+                    None => ()
+                }
+
+                gl.SamplerParameterf(id, gl::TEXTURE_LOD_BIAS, behavior.lod_bias);
+
+                let (r, g, b, a) = behavior.border_color;
+                gl.SamplerParameterfv(id, gl::TEXTURE_BORDER_COLOR, [r, g, b, a].as_ptr());
+
+                match behavior.depth_compare {
+                    Some(function) => {
+                        gl.SamplerParameteri(id, gl::TEXTURE_COMPARE_MODE,
+                            gl::COMPARE_REF_TO_TEXTURE as gl::types::GLint);
+                        gl.SamplerParameteri(id, gl::TEXTURE_COMPARE_FUNC,
+                            function.get_gl_enum() as gl::types::GLint);
+                    },
+                    None => {
+                        gl.SamplerParameteri(id, gl::TEXTURE_COMPARE_MODE,
+                            gl::NONE as gl::types::GLint);
+                    }
+                }
+
+                id
+            }
+        }).get();
+
+        SamplerObject {
+            display: display.clone(),
+            id: id,
+            behavior: behavior.clone(),
+        }
+    }
+
+    /// Builds a new sampler object.
+    #[cfg(target_os = "android")]
+    pub fn new(display: &Arc<super::DisplayImpl>, behavior: &super::SamplerBehavior)
+        -> SamplerObject
+    {
+        if behavior.lod_bias != 0.0 {
+            fail!("LOD bias is not supported by GLES");
+        }
+
+        if behavior.depth_compare.is_some() {
+            fail!("Depth comparison is not supported by GLES");
+        }
+
+        SamplerObject {
+            display: display.clone(),
+            id: 0,
+            behavior: behavior.clone(),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+impl Drop for SamplerObject {
+    fn drop(&mut self) {
+        let id = self.id.clone();
+        self.display.context.exec(proc(gl) {
+            unsafe { gl.DeleteSamplers(1, [ id ].as_ptr()); }
+        });
+    }
+}
+
+/// Binds a sampler to a texture unit, or unbinds the sampler of this unit if `sampler`
+///  is `None`.
+///
+/// The texture must already be bound to the unit. `max_anisotropy` is the maximum level of
+///  anisotropic filtering supported by the implementation.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn bind_sampler(gl: &gl::Gl, texture_unit: gl::types::GLuint, _: gl::types::GLenum,
+                    sampler: Option<&SamplerObject>, _: Option<f32>)
+{
+    gl.BindSampler(texture_unit, sampler.map(|s| s.id).unwrap_or(0));
+}
+
+/// Binds a sampler to a texture unit, or unbinds the sampler of this unit if `sampler`
+///  is `None`.
+///
+/// The texture must already be bound to the unit. Since GLES has no sampler objects,
+///  the parameters are written in the texture and stay there after the draw, so the
+///  default parameters of textures are written back if `sampler` is `None`.
+///  `max_anisotropy` is the maximum level of anisotropic filtering supported by the
+///  implementation.
+#[cfg(target_os = "android")]
+pub fn bind_sampler(gl: &gl::Gl, _: gl::types::GLuint, bind_point: gl::types::GLenum,
+                    sampler: Option<&SamplerObject>, max_anisotropy: Option<f32>)
+{
+    let behavior = match sampler {
+        Some(s) => s.behavior.clone(),
+        // the default behavior has the same parameters as newly-created GLES textures
+        None => ::std::default::Default::default()
+    };
+
+    let (wrap_s, wrap_t, _) = behavior.wrap_function;
+    gl.TexParameteri(bind_point, gl::TEXTURE_WRAP_S, wrap_s.get_gl_enum() as gl::types::GLint);
+    gl.TexParameteri(bind_point, gl::TEXTURE_WRAP_T, wrap_t.get_gl_enum() as gl::types::GLint);
+
+    gl.TexParameteri(bind_point, gl::TEXTURE_MIN_FILTER,
+        behavior.get_minify_filter() as gl::types::GLint);
+    gl.TexParameteri(bind_point, gl::TEXTURE_MAG_FILTER,
+        behavior.magnify_filter.get_gl_enum() as gl::types::GLint);
+
+    // a level of 1 disables anisotropic filtering if it was enabled by a previous sampler
+    if max_anisotropy.is_some() {
+        let value = get_max_anisotropy(max_anisotropy, behavior.max_anisotropy).unwrap_or(1.0);
+        gl.TexParameterf(bind_point, TEXTURE_MAX_ANISOTROPY_EXT, value);
+    }
+}
+
+/// Returns the anisotropy level to use, or `None` if anisotropic filtering is not requested
+///  or not supported.
+fn get_max_anisotropy(supported: Option<f32>, requested: u16) -> Option<f32> {
+    match supported {
+        Some(max) if requested > 1 => {
+            Some(if (requested as f32) < max { requested as f32 } else { max })
+        },
+        _ => None
+    }
+}
+
+/// Returns the maximum level of anisotropic filtering supported by the implementation, or
+///  `None` if the `GL_EXT_texture_filter_anisotropic` extension is not available.
+///
+/// This public function is accessible from within `simple_gl` but not for the user.
+pub fn get_max_supported_anisotropy(gl: &gl::Gl) -> Option<f32> {
+    if !is_anisotropy_supported(gl) {
+        return None;
+    }
+
+    unsafe {
+        let mut max: gl::types::GLfloat = mem::uninitialized();
+        gl.GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max);
+        Some(max)
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn is_anisotropy_supported(gl: &gl::Gl) -> bool {
+    unsafe {
+        let mut num_extensions: gl::types::GLint = mem::uninitialized();
+        gl.GetIntegerv(gl::NUM_EXTENSIONS, &mut num_extensions);
+
+        range(0, num_extensions).any(|i| {
+            let name = gl.GetStringi(gl::EXTENSIONS, i as gl::types::GLuint);
+            let name = CString::new(name as *const libc::c_char, false);
+            name.as_str() == Some("GL_EXT_texture_filter_anisotropic")
+        })
+    }
+}
+
+#[cfg(target_os = "android")]
+fn is_anisotropy_supported(gl: &gl::Gl) -> bool {
+    unsafe {
+        let extensions = gl.GetString(gl::EXTENSIONS);
+        let extensions = CString::new(extensions as *const libc::c_char, false);
+        extensions.as_str().map(|e| e.split(' ').any(|e| e == "GL_EXT_texture_filter_anisotropic"))
+            .unwrap_or(false)
+    }
+}
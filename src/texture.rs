@@ -1,8 +1,10 @@
 use data_types;
+use framebuffer;
 use gl;
 use libc;
 use std::fmt;
 use std::mem;
+use std::ptr;
 use std::sync::Arc;
 
 /// A texture usable by OpenGL.
@@ -16,14 +18,84 @@ pub fn get_impl<'a>(texture: &'a Texture) -> &'a Arc<TextureImpl> {
 
 impl Texture {
     /// Builds a new texture.
-    #[cfg(target_os = "windows")]
-    #[cfg(target_os = "linux")]
-    #[cfg(target_os = "macos")]
+    ///
+    /// The texels are stored as RGBA values whose precision is chosen by the implementation.
+    /// Use `new_with_format` to choose the format in which the texture is stored.
     pub fn new<T: data_types::GLDataTuple>(display: &super::Display, data: &[T], width: uint, height: uint, depth: uint, array_size: uint)
         -> Texture
     {
         let element_components = data_types::GLDataTuple::get_num_elems(None::<T>);
+        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
+
+        let data_format = match get_client_format(FloatFormat, element_components as uint, data_type) {
+            Some((format, _)) => format,
+            None => fail!("unsupported texture type")
+        };
+
+        Texture::new_impl(display, get_default_internal_format(data_format), data_format,
+            data_type, FloatFormat, data, width, height, depth, array_size)
+    }
+
+    /// Builds a new texture whose texels are stored in the given format.
+    ///
+    /// The type of the data must be compatible with the format: it must have the same number
+    /// of components, integer formats require integer data of the same signedness, depth
+    /// formats require `u16`, `u32` or `f32` data, and `Depth24Stencil8` requires `u32` data
+    /// which contains the depth in its 24 higher bits and the stencil value in its 8 lower
+    /// bits.
+    ///
+    /// Returns an error if the format is not supported by the context or if the data is not
+    /// compatible with it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// let data: Vec<(f32, f32, f32, f32)> = Vec::from_elem(64 * 64, (1.0, 1.0, 1.0, 1.0));
+    /// let texture = simple_gl::Texture::new_with_format(&display, simple_gl::RGBA32F,
+    ///     data.as_slice(), 64, 64, 1, 1).unwrap();
+    /// ```
+    ///
+    pub fn new_with_format<T: data_types::GLDataTuple>(display: &super::Display,
+                                                       format: super::TextureFormat, data: &[T],
+                                                       width: uint, height: uint, depth: uint,
+                                                       array_size: uint)
+        -> Result<Texture, super::TextureCreationError>
+    {
+        let (internal_format, components, kind, version) = match get_format_infos(format) {
+            Some(infos) => infos,
+            None => return Err(super::FormatNotSupported(format))
+        };
+
+        if display.context.gl_version < version {
+            return Err(super::FormatNotSupported(format));
+        }
+
+        let element_components = data_types::GLDataTuple::get_num_elems(None::<T>) as uint;
+        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
 
+        if element_components != components {
+            return Err(super::IncompatibleDataType(format, element_components));
+        }
+
+        let (data_format, data_type) = match get_client_format(kind, element_components, data_type) {
+            Some(f) => f,
+            None => return Err(super::IncompatibleDataType(format, element_components))
+        };
+
+        Ok(Texture::new_impl(display, internal_format, data_format, data_type, kind, data, width,
+            height, depth, array_size))
+    }
+
+    /// Builds a new texture.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    fn new_impl<T>(display: &super::Display, internal_format: gl::types::GLenum,
+                   data_format: gl::types::GLenum, data_type: gl::types::GLenum, kind: FormatKind,
+                   data: &[T], width: uint, height: uint, depth: uint, array_size: uint)
+        -> Texture
+    {
         if width * height * depth * array_size != data.len() {
             fail!("Texture data has different size from width*height*depth*array_size*elemLen");
         }
@@ -36,16 +108,11 @@ impl Texture {
             gl::TEXTURE_3D
         };
 
-        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
         let data_raw: *const libc::c_void = unsafe { mem::transmute(data.as_ptr()) };
 
-        let (data_format, data_type) = match (element_components, data_type) {
-            (1, f) => (gl::RED, f),
-            (2, f) => (gl::RG, f),
-            (3, f) => (gl::RGB, f),
-            (4, f) => (gl::RGBA, f),
-            _ => fail!("unsupported texture type")
-        };
+        // mipmaps can't be generated for integer and depth textures, and these textures are
+        //  incomplete if they are sampled with a linear filter
+        let generate_mipmaps = kind == FloatFormat;
 
         let id = display.context.context.exec(proc(gl) {
             unsafe {
@@ -63,18 +130,25 @@ impl Texture {
                 if depth != 1 || array_size != 1 {
                     gl.TexParameteri(texture_type, gl::TEXTURE_WRAP_R, gl::REPEAT as i32);
                 }
-                gl.TexParameteri(texture_type, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-                gl.TexParameteri(texture_type, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+                if generate_mipmaps {
+                    gl.TexParameteri(texture_type, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                    gl.TexParameteri(texture_type, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+                } else {
+                    gl.TexParameteri(texture_type, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                    gl.TexParameteri(texture_type, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                }
 
                 if texture_type == gl::TEXTURE_3D || texture_type == gl::TEXTURE_2D_ARRAY {
-                    gl.TexImage3D(texture_type, 0, gl::RGBA as i32, width as i32, height as i32, if depth > 1 { depth } else { array_size } as i32, 0, data_format as u32, data_type, data_raw);
+                    gl.TexImage3D(texture_type, 0, internal_format as i32, width as i32, height as i32, if depth > 1 { depth } else { array_size } as i32, 0, data_format as u32, data_type, data_raw);
                 } else if texture_type == gl::TEXTURE_2D || texture_type == gl::TEXTURE_1D_ARRAY {
-                    gl.TexImage2D(texture_type, 0, gl::RGBA as i32, width as i32, height as i32, 0, data_format as u32, data_type, data_raw);
+                    gl.TexImage2D(texture_type, 0, internal_format as i32, width as i32, if height > 1 { height } else { array_size } as i32, 0, data_format as u32, data_type, data_raw);
                 } else {
-                    gl.TexImage1D(texture_type, 0, gl::RGBA as i32, width as i32, 0, data_format as u32, data_type, data_raw);
+                    gl.TexImage1D(texture_type, 0, internal_format as i32, width as i32, 0, data_format as u32, data_type, data_raw);
                 }
 
-                gl.GenerateMipmap(texture_type);
+                if generate_mipmaps {
+                    gl.GenerateMipmap(texture_type);
+                }
 
                 id
             }
@@ -85,6 +159,7 @@ impl Texture {
                 display: display.context.clone(),
                 id: id,
                 bind_point: texture_type,
+                internal_format: internal_format,
                 width: width,
                 height: height,
                 depth: depth,
@@ -95,11 +170,11 @@ impl Texture {
 
     /// Builds a new texture.
     #[cfg(target_os = "android")]
-    pub fn new<T: data_types::GLDataTuple>(display: &super::Display, data: &[T], width: uint, height: uint, depth: uint, array_size: uint)
+    fn new_impl<T>(display: &super::Display, internal_format: gl::types::GLenum,
+                   data_format: gl::types::GLenum, data_type: gl::types::GLenum, _: FormatKind,
+                   data: &[T], width: uint, height: uint, depth: uint, array_size: uint)
         -> Texture
     {
-        let element_components = data_types::GLDataTuple::get_num_elems(None::<T>);
-
         if depth != 1 || array_size != 1 {
             fail!("GLES does not support 3D textures or texture arrays")
         }
@@ -108,15 +183,8 @@ impl Texture {
             fail!("Texture data has different size from width*height*depth*array_size*elemLen");
         }
 
-        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
         let data_raw: *const libc::c_void = unsafe { mem::transmute(data.as_ptr()) };
 
-        let (data_format, data_type) = match (element_components, data_type) {
-            (3, f) => (gl::RGB, f),
-            (4, f) => (gl::RGBA, f),
-            _ => fail!("unsupported texture type")
-        };
-
         let id = display.context.context.exec(proc(gl) {
             unsafe {
                 gl.PixelStorei(gl::UNPACK_ALIGNMENT, if width % 4 == 0 { 4 } else if height % 2 == 0 { 2 } else { 1 });
@@ -131,7 +199,7 @@ impl Texture {
                 gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
                 gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
 
-                gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, width as i32, height as i32, 0, data_format as u32, data_type, data_raw);
+                gl.TexImage2D(gl::TEXTURE_2D, 0, internal_format as i32, width as i32, height as i32, 0, data_format as u32, data_type, data_raw);
 
                 gl.GenerateMipmap(gl::TEXTURE_2D);
 
@@ -144,6 +212,7 @@ impl Texture {
                 display: display.context.clone(),
                 id: id,
                 bind_point: gl::TEXTURE_2D,
+                internal_format: internal_format,
                 width: width,
                 height: height,
                 depth: depth,
@@ -152,6 +221,205 @@ impl Texture {
         }
     }
 
+    /// Builds a new cubemap texture.
+    ///
+    /// `faces` contains the texels of each face, in the order `PositiveX`, `NegativeX`,
+    /// `PositiveY`, `NegativeY`, `PositiveZ` and `NegativeZ`. Each face is a square of
+    /// `size` by `size` texels.
+    ///
+    /// Cubemaps must be bound to a `samplerCube` in GLSL.
+    pub fn new_cubemap<T: data_types::GLDataTuple>(display: &super::Display, faces: [&[T], ..6],
+                                                   size: uint)
+        -> Texture
+    {
+        let element_components = data_types::GLDataTuple::get_num_elems(None::<T>);
+        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
+
+        let data_format = match get_client_format(FloatFormat, element_components as uint, data_type) {
+            Some((format, _)) => format,
+            None => fail!("unsupported texture type")
+        };
+        let internal_format = get_default_internal_format(data_format);
+
+        for face in faces.iter() {
+            if face.len() != size * size {
+                fail!("Each face of a cubemap must contain size*size texels");
+            }
+        }
+
+        let faces_raw: Vec<*const libc::c_void> = faces.iter()
+            .map(|face| unsafe { mem::transmute(face.as_ptr()) }).collect();
+
+        let id = display.context.context.exec(proc(gl) {
+            unsafe {
+                gl.PixelStorei(gl::UNPACK_ALIGNMENT, if size % 4 == 0 { 4 } else if size % 2 == 0 { 2 } else { 1 });
+
+                let id: gl::types::GLuint = mem::uninitialized();
+                gl.GenTextures(1, mem::transmute(&id));
+
+                gl.BindTexture(gl::TEXTURE_CUBE_MAP, id);
+
+                gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl.TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+
+                // the six faces have consecutive values, in the same order as `faces`
+                for (offset, data_raw) in faces_raw.iter().enumerate() {
+                    gl.TexImage2D(gl::TEXTURE_CUBE_MAP_POSITIVE_X + offset as gl::types::GLenum, 0, internal_format as i32, size as i32, size as i32, 0, data_format as u32, data_type, *data_raw);
+                }
+
+                gl.GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+
+                id
+            }
+        }).get();
+
+        Texture {
+            texture: Arc::new(TextureImpl {
+                display: display.context.clone(),
+                id: id,
+                bind_point: gl::TEXTURE_CUBE_MAP,
+                internal_format: internal_format,
+                width: size,
+                height: size,
+                depth: 1,
+                array_size: 1
+            })
+        }
+    }
+
+    /// Builds a new texture that can be used as a depth buffer when drawing on another texture.
+    ///
+    /// The content of the texture is undefined until something is drawn.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new_depth(display: &super::Display, width: uint, height: uint) -> Texture {
+        let id = display.context.context.exec(proc(gl) {
+            unsafe {
+                let id: gl::types::GLuint = mem::uninitialized();
+                gl.GenTextures(1, mem::transmute(&id));
+
+                gl.BindTexture(gl::TEXTURE_2D, id);
+
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+
+                gl.TexImage2D(gl::TEXTURE_2D, 0, gl::DEPTH_COMPONENT24 as i32, width as i32, height as i32, 0, gl::DEPTH_COMPONENT, gl::FLOAT, ptr::null());
+
+                id
+            }
+        }).get();
+
+        Texture {
+            texture: Arc::new(TextureImpl {
+                display: display.context.clone(),
+                id: id,
+                bind_point: gl::TEXTURE_2D,
+                internal_format: gl::DEPTH_COMPONENT24,
+                width: width,
+                height: height,
+                depth: 1,
+                array_size: 1
+            })
+        }
+    }
+
+    /// Builds a new texture that can be used as a depth buffer when drawing on another texture.
+    #[cfg(target_os = "android")]
+    pub fn new_depth(_: &super::Display, _: uint, _: uint) -> Texture {
+        fail!("Depth textures are not supported by GLES")
+    }
+
+    /// Builds a new multisampled texture, which can be drawn on but not sampled with a
+    /// regular sampler.
+    ///
+    /// Draw on it with a `FramebufferBuilder` or `Texture::draw`, then use `Target::resolve` to
+    /// copy the result to a regular texture or to the display. All the attachments of a
+    /// framebuffer must have the same number of samples.
+    ///
+    /// Returns an error if the format or the number of samples is not supported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// let texture = simple_gl::Texture::new_multisample(&display, simple_gl::RGBA8,
+    ///     1024, 768, 4).unwrap();
+    /// ```
+    ///
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new_multisample(display: &super::Display, format: super::TextureFormat, width: uint,
+                           height: uint, samples: uint)
+        -> Result<Texture, super::TextureCreationError>
+    {
+        let (internal_format, _, kind, version) = match get_format_infos(format) {
+            Some(infos) => infos,
+            None => return Err(super::FormatNotSupported(format))
+        };
+
+        if display.context.gl_version < version || display.context.gl_version < (3, 2) {
+            return Err(super::FormatNotSupported(format));
+        }
+
+        let max_samples_enum = match kind {
+            DepthFormat | DepthStencilFormat => gl::MAX_DEPTH_TEXTURE_SAMPLES,
+            SignedIntegerFormat | UnsignedIntegerFormat => gl::MAX_INTEGER_SAMPLES,
+            FloatFormat => gl::MAX_COLOR_TEXTURE_SAMPLES,
+        };
+
+        let id = display.context.context.exec(proc(gl) {
+            unsafe {
+                let mut max_samples: gl::types::GLint = mem::uninitialized();
+                gl.GetIntegerv(max_samples_enum, &mut max_samples);
+                if samples == 0 || samples > max_samples as uint {
+                    return None;
+                }
+
+                let id: gl::types::GLuint = mem::uninitialized();
+                gl.GenTextures(1, mem::transmute(&id));
+
+                gl.BindTexture(gl::TEXTURE_2D_MULTISAMPLE, id);
+                gl.TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples as gl::types::GLsizei,
+                    internal_format as gl::types::GLint, width as gl::types::GLsizei,
+                    height as gl::types::GLsizei, gl::TRUE);
+
+                Some(id)
+            }
+        }).get();
+
+        let id = match id {
+            Some(id) => id,
+            None => return Err(super::SamplesCountNotSupported(samples))
+        };
+
+        Ok(Texture {
+            texture: Arc::new(TextureImpl {
+                display: display.context.clone(),
+                id: id,
+                bind_point: gl::TEXTURE_2D_MULTISAMPLE,
+                internal_format: internal_format,
+                width: width,
+                height: height,
+                depth: 1,
+                array_size: 1
+            })
+        })
+    }
+
+    /// Builds a new multisampled texture.
+    #[cfg(target_os = "android")]
+    pub fn new_multisample(_: &super::Display, _: super::TextureFormat, _: uint, _: uint, _: uint)
+        -> Result<Texture, super::TextureCreationError>
+    {
+        fail!("Multisampled textures are not supported by GLES")
+    }
+
     /// Returns the width of the texture.
     pub fn get_width(&self) -> uint {
         self.texture.width
@@ -172,98 +440,577 @@ impl Texture {
         self.texture.array_size
     }
 
+    /// Returns true if the texture is a cubemap.
+    pub fn is_cubemap(&self) -> bool {
+        self.texture.bind_point == gl::TEXTURE_CUBE_MAP
+    }
+
+    /// Returns true if the texture has been created with `new_multisample`.
+    pub fn is_multisample(&self) -> bool {
+        is_multisample_bind_point(self.texture.bind_point)
+    }
+
     /// Start drawing on this texture.
-    #[cfg(target_os = "windows")]
-    #[cfg(target_os = "linux")]
-    #[cfg(target_os = "macos")]
     pub fn draw(&mut self) -> super::Target {
-        let display = self.texture.display.clone();
-        let fbo = super::FrameBufferObject::new(display.clone());
+        self.draw_with_attachments(&[])
+    }
 
-        // binding the texture to the FBO
-        {
-            let my_id = self.texture.id.clone();
-            let fbo_id = fbo.id;
-            self.texture.display.context.exec(proc(gl) {
-                gl.BindFramebuffer(gl::FRAMEBUFFER, fbo_id);
-                gl.FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, my_id, 0);
-            });
+    /// Start drawing on this texture, with depth and/or stencil buffers.
+    ///
+    /// The attachments must have the same dimensions as the texture. Drawing on a texture
+    /// with a depth buffer is required in order to use depth testing.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// # let mut texture: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+    /// let depth = simple_gl::RenderBuffer::new_depth(&display, texture.get_width(),
+    ///     texture.get_height());
+    ///
+    /// let mut target = texture.draw_with_attachments(&[
+    ///     simple_gl::RenderBufferAttachment(&depth)
+    /// ]);
+    /// target.clear_depth(1.0);
+    /// ```
+    ///
+    pub fn draw_with_attachments<'t>(&'t mut self,
+                                     attachments: &[super::DepthStencilAttachment<'t>])
+        -> super::Target<'t>
+    {
+        if self.is_cubemap() {
+            fail!("Use `draw_face` to draw on a cubemap");
         }
 
-        // returning the target
-        super::Target {
-            display: display,
-            display_hold: None,
-            texture: Some(self),
-            framebuffer: Some(fbo),
-            execute_end: None,
+        self.draw_impl(None, attachments)
+    }
+
+    /// Start drawing on one face of this cubemap.
+    ///
+    /// Fails if the texture is not a cubemap.
+    pub fn draw_face(&mut self, face: super::CubeMapFace) -> super::Target {
+        self.draw_face_with_attachments(face, &[])
+    }
+
+    /// Start drawing on one face of this cubemap, with depth and/or stencil buffers.
+    ///
+    /// The attachments must have the same dimensions as a face of the cubemap.
+    /// Fails if the texture is not a cubemap.
+    pub fn draw_face_with_attachments<'t>(&'t mut self, face: super::CubeMapFace,
+                                          attachments: &[super::DepthStencilAttachment<'t>])
+        -> super::Target<'t>
+    {
+        if !self.is_cubemap() {
+            fail!("The texture is not a cubemap");
         }
+
+        self.draw_impl(Some(face.get_gl_enum()), attachments)
     }
 
-    /// Start drawing on this texture.
-    #[cfg(target_os = "android")]
-    pub fn draw(&mut self) -> super::Target {
+    /// Builds a target that draws on the texture, or on one face of the texture if it is
+    /// a cubemap.
+    fn draw_impl<'t>(&'t mut self, face: Option<gl::types::GLenum>,
+                     attachments: &[super::DepthStencilAttachment<'t>])
+        -> super::Target<'t>
+    {
         let display = self.texture.display.clone();
-        let fbo = super::FrameBufferObject::new(display.clone());
-
-        // binding the texture to the FBO
+        let fbo = match framebuffer::FrameBufferObject::new(display.clone(),
+            &[(None, &*self.texture, face)], attachments)
         {
-            let my_id = self.texture.id.clone();
-            let fbo_id = fbo.id;
-            self.texture.display.context.exec(proc(gl) {
-                gl.BindFramebuffer(gl::FRAMEBUFFER, fbo_id);
-                gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, my_id, 0);
-            });
-        }
+            Ok(fbo) => fbo,
+            Err(err) => fail!("{}", err)
+        };
 
-        // returning the target
         super::Target {
             display: display,
             display_hold: None,
-            texture: Some(self),
-            framebuffer: Some(fbo),
+            textures: vec![&*self],
+            dimensions: fbo.dimensions,
+            viewport: None,
+            scissor: None,
+            framebuffer: Some(Arc::new(fbo)),
             execute_end: None,
         }
     }
 
-    /// Reads the content of the texture.
+    /// Uploads data to a part of the first mipmap of the texture, then regenerates the
+    /// other mipmaps.
     ///
-    /// Same as `read_mipmap` with `level` as `0`.
-    // TODO: draft ; must be checked and turned public
-    fn read(&self) -> Vec<u8> {
-        self.read_mipmap(0)
+    /// Same as `write_mipmap` with `level` and `layer` as `0`.
+    pub fn write<T: data_types::GLDataTuple>(&mut self, offset: (uint, uint, uint),
+                                             size: (uint, uint, uint), data: &[T])
+    {
+        self.write_mipmap(0, 0, offset, size, data, true)
     }
 
-    /// Reads the content of one of the mipmaps the texture.
+    /// Uploads data to a part of one of the mipmaps of the texture.
     ///
-    /// Returns a 2D array of pixels.
-    /// Each pixel has R, G and B components between 0 and 255.
-    // TODO: draft ; must be checked and turned public
-    fn read_mipmap(&self, level: uint) -> Vec<u8> {
-        unimplemented!()
-        /*let bind_point = self.texture.bind_point;
-        let id = self.texture.id;
-        let buffer_size = self.texture.width * self.texture.height * self.texture.depth *
-            self.texture.array_size * 3;
+    /// `offset` and `size` are the position and the dimensions, in texels, of the box to
+    /// modify. Use a height and a depth of `1` for 1D textures and a depth of `1` for 2D
+    /// textures. `layer` is the index of the element to modify in a texture array, or the
+    /// index of the face in a cubemap (in the same order as in `new_cubemap`), and must be
+    /// `0` otherwise.
+    ///
+    /// If `regenerate_mipmaps` is true, all the mipmaps are then regenerated from the first
+    /// one. Integer and depth textures don't have mipmaps.
+    ///
+    /// Fails if the box is out of the bounds of the texture, or if the length of `data`
+    /// is not the number of texels of the box.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let mut texture: simple_gl::Texture = unsafe { std::mem::uninitialized() };
+    /// // replacing a 16x16 glyph of an atlas
+    /// let glyph: Vec<(u8, u8, u8, u8)> = Vec::from_elem(16 * 16, (255, 255, 255, 255));
+    /// texture.write_mipmap(0, 0, (32, 48, 0), (16, 16, 1), glyph.as_slice(), false);
+    /// ```
+    ///
+    pub fn write_mipmap<T: data_types::GLDataTuple>(&mut self, level: uint, layer: uint,
+                                                    offset: (uint, uint, uint),
+                                                    size: (uint, uint, uint), data: &[T],
+                                                    regenerate_mipmaps: bool)
+    {
+        if self.is_multisample() {
+            fail!("Multisampled textures can't be written, draw on them instead");
+        }
+
+        let (mipmap_width, mipmap_height, mipmap_depth) = self.get_mipmap_dimensions(level);
+        let (x, y, z) = offset;
+        let (width, height, depth) = size;
+
+        if x + width > mipmap_width || y + height > mipmap_height || z + depth > mipmap_depth {
+            fail!("The box to write is out of the bounds of the texture ({}x{}x{} at level {})",
+                mipmap_width, mipmap_height, mipmap_depth, level);
+        }
 
-        if level != 0 {
-            unimplemented!()
+        let layers = if self.is_cubemap() { 6 } else { self.texture.array_size };
+        if layer >= layers {
+            fail!("Layer {} is out of range for a texture with {} layers", layer, layers);
         }
 
+        if width * height * depth != data.len() {
+            fail!("Texture data has different size from width*height*depth");
+        }
+
+        let kind = get_format_kind(self.texture.internal_format);
+        let regenerate_mipmaps = regenerate_mipmaps && kind == FloatFormat;
+
+        let components = data_types::GLDataTuple::get_num_elems(None::<T>) as uint;
+        let data_type = data_types::GLDataTuple::get_gl_type(None::<T>);
+        let (format, data_type) = match get_client_format(kind, components, data_type) {
+            Some(f) => f,
+            None => fail!("unsupported texture type for this texture format")
+        };
+
+        let data_raw: *const libc::c_void = unsafe { mem::transmute(data.as_ptr()) };
+        let bind_point = self.texture.bind_point;
+        let id = self.texture.id;
+
         self.texture.display.context.exec(proc(gl) {
-            let mut buffer = Vec::from_elem(buffer_size, 0u8);
+            gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl.BindTexture(bind_point, id);
+
+            upload_sub_image(gl, bind_point, level, layer, offset, size, format, data_type,
+                data_raw);
+
+            if regenerate_mipmaps {
+                gl.GenerateMipmap(bind_point);
+            }
+        }).get();
+    }
+
+    /// Reads the content of the texture.
+    ///
+    /// Same as `read_mipmap` with `level` and `layer` as `0`.
+    pub fn read<P: data_types::GLDataTuple + Send>(&self) -> Vec<P> {
+        self.read_mipmap(0, 0)
+    }
+
+    /// Reads the content of one of the mipmaps of the texture.
+    ///
+    /// `layer` is the index of the element to read in a texture array, and must be `0` if the
+    /// texture is not an array.
+    ///
+    /// The type of pixel that you request determines the components that are returned and
+    /// their type. For example `(u8, u8, u8, u8)` returns RGBA values between 0 and 255, while
+    /// `f32` only returns the red component as a float. Integer textures must be read with
+    /// integers of the same signedness, and depth textures with a single component.
+    ///
+    /// Returns the texels row by row, starting with the bottom row, and for 3D textures slice
+    /// by slice.
+    ///
+    /// Fails if the texture is a cubemap. Use `read_face` instead.
+    pub fn read_mipmap<P: data_types::GLDataTuple + Send>(&self, level: uint, layer: uint)
+        -> Vec<P>
+    {
+        if self.is_cubemap() {
+            fail!("Use `read_face` to read a cubemap");
+        }
+
+        if self.is_multisample() {
+            fail!("Multisampled textures can't be read, resolve them to a regular texture first");
+        }
+
+        self.read_impl(self.texture.bind_point, level, layer)
+    }
+
+    /// Reads the content of one face of this cubemap.
+    ///
+    /// See `read_mipmap` for the format of the result. Fails if the texture is not a cubemap.
+    pub fn read_face<P: data_types::GLDataTuple + Send>(&self, face: super::CubeMapFace)
+        -> Vec<P>
+    {
+        if !self.is_cubemap() {
+            fail!("The texture is not a cubemap");
+        }
+
+        self.read_impl(face.get_gl_enum(), 0, 0)
+    }
+
+    /// Reads the content of a mipmap of the texture, or of a face of a cubemap.
+    ///
+    /// `target` is the bind point of the texture, or the face to read.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    fn read_impl<P: data_types::GLDataTuple + Send>(&self, target: gl::types::GLenum,
+                                                     level: uint, layer: uint)
+        -> Vec<P>
+    {
+        let (width, height, depth) = self.get_mipmap_dimensions(level);
+
+        if layer >= self.texture.array_size {
+            fail!("Layer {} is out of range for a texture array of {} elements", layer,
+                self.texture.array_size);
+        }
+
+        let pixels_per_layer = width * height * depth;
+        let buffer_size = pixels_per_layer * self.texture.array_size;
+
+        let components = data_types::GLDataTuple::get_num_elems(None::<P>) as uint;
+        let data_type = data_types::GLDataTuple::get_gl_type(None::<P>);
+        let (format, data_type) = match get_client_format(get_format_kind(self.texture.internal_format), components, data_type) {
+            Some(f) => f,
+            None => fail!("unsupported pixel type for this texture format")
+        };
+
+        let bind_point = self.texture.bind_point;
+        let id = self.texture.id;
+
+        let buffer = self.texture.display.context.exec(proc(gl) {
+            let mut buffer: Vec<P> = Vec::with_capacity(buffer_size);
 
             unsafe {
+                gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
                 gl.BindTexture(bind_point, id);
-                gl.GetTexImage(bind_point, 0 as gl::types::GLint, gl::RGBA_INTEGER, gl::UNSIGNED_BYTE,
+                gl.GetTexImage(target, level as gl::types::GLint, format, data_type,
                     buffer.as_mut_ptr() as *mut libc::c_void);
+                buffer.set_len(buffer_size);
             }
 
             buffer
-        }).get()*/
+        }).get();
+
+        // `glGetTexImage` returns all the layers of the array, so we only keep the one we want
+        buffer.move_iter().skip(layer * pixels_per_layer).take(pixels_per_layer).collect()
+    }
+
+    /// Reads the content of a mipmap of the texture, or of a face of a cubemap.
+    #[cfg(target_os = "android")]
+    fn read_impl<P: data_types::GLDataTuple + Send>(&self, _: gl::types::GLenum, _: uint, _: uint)
+        -> Vec<P>
+    {
+        fail!("Reading textures is not supported by GLES")
+    }
+
+    /// Returns the width, height and depth of one of the mipmaps of the texture.
+    ///
+    /// Fails if the mipmap level doesn't exist.
+    fn get_mipmap_dimensions(&self, level: uint) -> (uint, uint, uint) {
+        use std::cmp;
+
+        let ref tex = self.texture;
+        let biggest = cmp::max(tex.width, cmp::max(tex.height, tex.depth));
+
+        if level != 0 && (biggest >> level) == 0 {
+            fail!("Mipmap level {} doesn't exist in this texture", level);
+        }
+
+        (cmp::max(1, tex.width >> level), cmp::max(1, tex.height >> level),
+            cmp::max(1, tex.depth >> level))
     }
 }
 
+/// Kind of values stored by a texture format.
+#[deriving(PartialEq, Eq)]
+enum FormatKind {
+    /// Floating-point or normalized values.
+    FloatFormat,
+    SignedIntegerFormat,
+    UnsignedIntegerFormat,
+    DepthFormat,
+    DepthStencilFormat,
+}
+
+/// Returns the internal format, the number of components, the kind of values and the
+/// minimal OpenGL version required by a texture format, or `None` if the format is not
+/// supported at all.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_format_infos(format: super::TextureFormat)
+    -> Option<(gl::types::GLenum, uint, FormatKind, (gl::types::GLint, gl::types::GLint))>
+{
+    Some(match format {
+        super::R8 => (gl::R8, 1, FloatFormat, (3, 0)),
+        super::RG8 => (gl::RG8, 2, FloatFormat, (3, 0)),
+        super::RGB8 => (gl::RGB8, 3, FloatFormat, (1, 1)),
+        super::RGBA8 => (gl::RGBA8, 4, FloatFormat, (1, 1)),
+        super::R16 => (gl::R16, 1, FloatFormat, (3, 0)),
+        super::RG16 => (gl::RG16, 2, FloatFormat, (3, 0)),
+        super::RGB16 => (gl::RGB16, 3, FloatFormat, (1, 1)),
+        super::RGBA16 => (gl::RGBA16, 4, FloatFormat, (1, 1)),
+        super::R16F => (gl::R16F, 1, FloatFormat, (3, 0)),
+        super::RG16F => (gl::RG16F, 2, FloatFormat, (3, 0)),
+        super::RGB16F => (gl::RGB16F, 3, FloatFormat, (3, 0)),
+        super::RGBA16F => (gl::RGBA16F, 4, FloatFormat, (3, 0)),
+        super::R32F => (gl::R32F, 1, FloatFormat, (3, 0)),
+        super::RG32F => (gl::RG32F, 2, FloatFormat, (3, 0)),
+        super::RGB32F => (gl::RGB32F, 3, FloatFormat, (3, 0)),
+        super::RGBA32F => (gl::RGBA32F, 4, FloatFormat, (3, 0)),
+        super::R11FG11FB10F => (gl::R11F_G11F_B10F, 3, FloatFormat, (3, 0)),
+        super::R8I => (gl::R8I, 1, SignedIntegerFormat, (3, 0)),
+        super::RG8I => (gl::RG8I, 2, SignedIntegerFormat, (3, 0)),
+        super::RGB8I => (gl::RGB8I, 3, SignedIntegerFormat, (3, 0)),
+        super::RGBA8I => (gl::RGBA8I, 4, SignedIntegerFormat, (3, 0)),
+        super::R16I => (gl::R16I, 1, SignedIntegerFormat, (3, 0)),
+        super::RG16I => (gl::RG16I, 2, SignedIntegerFormat, (3, 0)),
+        super::RGB16I => (gl::RGB16I, 3, SignedIntegerFormat, (3, 0)),
+        super::RGBA16I => (gl::RGBA16I, 4, SignedIntegerFormat, (3, 0)),
+        super::R32I => (gl::R32I, 1, SignedIntegerFormat, (3, 0)),
+        super::RG32I => (gl::RG32I, 2, SignedIntegerFormat, (3, 0)),
+        super::RGB32I => (gl::RGB32I, 3, SignedIntegerFormat, (3, 0)),
+        super::RGBA32I => (gl::RGBA32I, 4, SignedIntegerFormat, (3, 0)),
+        super::R8UI => (gl::R8UI, 1, UnsignedIntegerFormat, (3, 0)),
+        super::RG8UI => (gl::RG8UI, 2, UnsignedIntegerFormat, (3, 0)),
+        super::RGB8UI => (gl::RGB8UI, 3, UnsignedIntegerFormat, (3, 0)),
+        super::RGBA8UI => (gl::RGBA8UI, 4, UnsignedIntegerFormat, (3, 0)),
+        super::R16UI => (gl::R16UI, 1, UnsignedIntegerFormat, (3, 0)),
+        super::RG16UI => (gl::RG16UI, 2, UnsignedIntegerFormat, (3, 0)),
+        super::RGB16UI => (gl::RGB16UI, 3, UnsignedIntegerFormat, (3, 0)),
+        super::RGBA16UI => (gl::RGBA16UI, 4, UnsignedIntegerFormat, (3, 0)),
+        super::R32UI => (gl::R32UI, 1, UnsignedIntegerFormat, (3, 0)),
+        super::RG32UI => (gl::RG32UI, 2, UnsignedIntegerFormat, (3, 0)),
+        super::RGB32UI => (gl::RGB32UI, 3, UnsignedIntegerFormat, (3, 0)),
+        super::RGBA32UI => (gl::RGBA32UI, 4, UnsignedIntegerFormat, (3, 0)),
+        super::SRGB8 => (gl::SRGB8, 3, FloatFormat, (2, 1)),
+        super::SRGB8Alpha8 => (gl::SRGB8_ALPHA8, 4, FloatFormat, (2, 1)),
+        super::Depth16 => (gl::DEPTH_COMPONENT16, 1, DepthFormat, (1, 4)),
+        super::Depth24 => (gl::DEPTH_COMPONENT24, 1, DepthFormat, (1, 4)),
+        super::Depth32F => (gl::DEPTH_COMPONENT32F, 1, DepthFormat, (3, 0)),
+        super::Depth24Stencil8 => (gl::DEPTH24_STENCIL8, 1, DepthStencilFormat, (3, 0)),
+    })
+}
+
+/// Returns the internal format, the number of components, the kind of values and the
+/// minimal OpenGL version required by a texture format, or `None` if the format is not
+/// supported at all.
+#[cfg(target_os = "android")]
+fn get_format_infos(format: super::TextureFormat)
+    -> Option<(gl::types::GLenum, uint, FormatKind, (gl::types::GLint, gl::types::GLint))>
+{
+    // GLES 2 requires the internal format to be the same as the client format
+    match format {
+        super::RGB8 => Some((gl::RGB, 3, FloatFormat, (2, 0))),
+        super::RGBA8 => Some((gl::RGBA, 4, FloatFormat, (2, 0))),
+        _ => None
+    }
+}
+
+/// Returns the client format and type to use when uploading data of the given type to a
+/// texture, or `None` if the data type is not compatible with this kind of format.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_client_format(kind: FormatKind, components: uint, data_type: gl::types::GLenum)
+    -> Option<(gl::types::GLenum, gl::types::GLenum)>
+{
+    let format = match (kind, components, data_type) {
+        (FloatFormat, 1, _) => gl::RED,
+        (FloatFormat, 2, _) => gl::RG,
+        (FloatFormat, 3, _) => gl::RGB,
+        (FloatFormat, 4, _) => gl::RGBA,
+
+        (SignedIntegerFormat, _, gl::BYTE) | (SignedIntegerFormat, _, gl::SHORT) |
+        (SignedIntegerFormat, _, gl::INT) | (UnsignedIntegerFormat, _, gl::UNSIGNED_BYTE) |
+        (UnsignedIntegerFormat, _, gl::UNSIGNED_SHORT) |
+        (UnsignedIntegerFormat, _, gl::UNSIGNED_INT) => match components {
+            1 => gl::RED_INTEGER,
+            2 => gl::RG_INTEGER,
+            3 => gl::RGB_INTEGER,
+            4 => gl::RGBA_INTEGER,
+            _ => return None
+        },
+
+        (DepthFormat, 1, gl::UNSIGNED_SHORT) | (DepthFormat, 1, gl::UNSIGNED_INT) |
+        (DepthFormat, 1, gl::FLOAT) => gl::DEPTH_COMPONENT,
+
+        (DepthStencilFormat, 1, gl::UNSIGNED_INT) => {
+            return Some((gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8));
+        },
+
+        _ => return None
+    };
+
+    Some((format, data_type))
+}
+
+/// Returns the client format and type to use when uploading data of the given type to a
+/// texture, or `None` if the data type is not compatible with this kind of format.
+#[cfg(target_os = "android")]
+fn get_client_format(kind: FormatKind, components: uint, data_type: gl::types::GLenum)
+    -> Option<(gl::types::GLenum, gl::types::GLenum)>
+{
+    match (kind, components) {
+        (FloatFormat, 3) => Some((gl::RGB, data_type)),
+        (FloatFormat, 4) => Some((gl::RGBA, data_type)),
+        _ => None
+    }
+}
+
+/// Returns the internal format used by `Texture::new`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_default_internal_format(_: gl::types::GLenum) -> gl::types::GLenum {
+    gl::RGBA
+}
+
+/// Returns the internal format used by `Texture::new`.
+#[cfg(target_os = "android")]
+fn get_default_internal_format(data_format: gl::types::GLenum) -> gl::types::GLenum {
+    data_format
+}
+
+/// Returns the kind of values stored by an internal format.
+#[cfg(target_os = "android")]
+fn get_format_kind(_: gl::types::GLenum) -> FormatKind {
+    FloatFormat
+}
+
+/// Uploads a box of texels to the texture currently bound to `bind_point`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn upload_sub_image(gl: &gl::Gl, bind_point: gl::types::GLenum, level: uint, layer: uint,
+                    (x, y, z): (uint, uint, uint), (width, height, depth): (uint, uint, uint),
+                    format: gl::types::GLenum, data_type: gl::types::GLenum,
+                    data: *const libc::c_void)
+{
+    let level = level as gl::types::GLint;
+
+    unsafe {
+        match bind_point {
+            gl::TEXTURE_1D => gl.TexSubImage1D(bind_point, level, x as i32, width as i32, format, data_type, data),
+            gl::TEXTURE_1D_ARRAY => gl.TexSubImage2D(bind_point, level, x as i32, layer as i32, width as i32, 1, format, data_type, data),
+            gl::TEXTURE_2D => gl.TexSubImage2D(bind_point, level, x as i32, y as i32, width as i32, height as i32, format, data_type, data),
+            gl::TEXTURE_CUBE_MAP => gl.TexSubImage2D(gl::TEXTURE_CUBE_MAP_POSITIVE_X + layer as gl::types::GLenum, level, x as i32, y as i32, width as i32, height as i32, format, data_type, data),
+            gl::TEXTURE_2D_ARRAY => gl.TexSubImage3D(bind_point, level, x as i32, y as i32, layer as i32, width as i32, height as i32, 1, format, data_type, data),
+            gl::TEXTURE_3D => gl.TexSubImage3D(bind_point, level, x as i32, y as i32, z as i32, width as i32, height as i32, depth as i32, format, data_type, data),
+            _ => unreachable!()
+        }
+    }
+}
+
+/// Uploads a box of texels to the texture currently bound to `bind_point`.
+#[cfg(target_os = "android")]
+fn upload_sub_image(gl: &gl::Gl, bind_point: gl::types::GLenum, level: uint, layer: uint,
+                    (x, y, _): (uint, uint, uint), (width, height, _): (uint, uint, uint),
+                    format: gl::types::GLenum, data_type: gl::types::GLenum,
+                    data: *const libc::c_void)
+{
+    let target = if bind_point == gl::TEXTURE_CUBE_MAP {
+        gl::TEXTURE_CUBE_MAP_POSITIVE_X + layer as gl::types::GLenum
+    } else {
+        bind_point
+    };
+
+    unsafe {
+        gl.TexSubImage2D(target, level as gl::types::GLint, x as i32, y as i32, width as i32,
+            height as i32, format, data_type, data);
+    }
+}
+
+/// Returns the kind of values stored by an internal format.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_format_kind(internal_format: gl::types::GLenum) -> FormatKind {
+    match internal_format {
+        gl::R8I | gl::RG8I | gl::RGB8I | gl::RGBA8I | gl::R16I | gl::RG16I | gl::RGB16I |
+        gl::RGBA16I | gl::R32I | gl::RG32I | gl::RGB32I | gl::RGBA32I => SignedIntegerFormat,
+        gl::R8UI | gl::RG8UI | gl::RGB8UI | gl::RGBA8UI | gl::R16UI | gl::RG16UI |
+        gl::RGB16UI | gl::RGBA16UI | gl::R32UI | gl::RG32UI | gl::RGB32UI |
+        gl::RGBA32UI => UnsignedIntegerFormat,
+        gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32 |
+        gl::DEPTH_COMPONENT32F => DepthFormat,
+        gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => DepthStencilFormat,
+        _ => FloatFormat
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn is_multisample_bind_point(bind_point: gl::types::GLenum) -> bool {
+    bind_point == gl::TEXTURE_2D_MULTISAMPLE
+}
+
+#[cfg(target_os = "android")]
+fn is_multisample_bind_point(_: gl::types::GLenum) -> bool {
+    false
+}
+
+/// Attaches the first level of a texture to the framebuffer currently bound.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn attach_texture(gl: &gl::Gl, attachment: gl::types::GLenum, _: gl::types::GLenum,
+                      id: gl::types::GLuint)
+{
+    gl.FramebufferTexture(gl::FRAMEBUFFER, attachment, id, 0);
+}
+
+/// Attaches the first level of a texture to the framebuffer currently bound.
+#[cfg(target_os = "android")]
+pub fn attach_texture(gl: &gl::Gl, attachment: gl::types::GLenum, bind_point: gl::types::GLenum,
+                      id: gl::types::GLuint)
+{
+    gl.FramebufferTexture2D(gl::FRAMEBUFFER, attachment, bind_point, id, 0);
+}
+
+/// Returns the framebuffer attachment point of a depth or depth-stencil texture, or `None` if
+/// the format is not a depth format.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn get_depth_stencil_attachment(internal_format: gl::types::GLenum) -> Option<gl::types::GLenum> {
+    match internal_format {
+        gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32 |
+        gl::DEPTH_COMPONENT32F => Some(gl::DEPTH_ATTACHMENT),
+        gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => Some(gl::DEPTH_STENCIL_ATTACHMENT),
+        _ => None
+    }
+}
+
+/// Returns the framebuffer attachment point of a depth or depth-stencil texture, or `None` if
+/// the format is not a depth format.
+#[cfg(target_os = "android")]
+pub fn get_depth_stencil_attachment(_: gl::types::GLenum) -> Option<gl::types::GLenum> {
+    None
+}
+
 impl fmt::Show for Texture {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
         (format!("Texture #{} (dimensions: {}x{}x{})", self.texture.id,
@@ -275,6 +1022,7 @@ pub struct TextureImpl {
     pub display: Arc<super::DisplayImpl>,
     pub id: gl::types::GLuint,
     pub bind_point: gl::types::GLenum,
+    pub internal_format: gl::types::GLenum,
     pub width: uint,
     pub height: uint,
     pub depth: uint,
@@ -289,3 +1037,70 @@ impl Drop for TextureImpl {
         });
     }
 }
+
+#[cfg(test, not(target_os = "android"))]
+mod tests {
+    use gl;
+    use super::{get_client_format, get_format_infos, get_format_kind};
+    use super::{FloatFormat, SignedIntegerFormat, UnsignedIntegerFormat, DepthFormat};
+    use super::{DepthStencilFormat};
+
+    static FORMATS: &'static [::TextureFormat] = &[
+        ::R8, ::RG8, ::RGB8, ::RGBA8, ::R16, ::RG16, ::RGB16, ::RGBA16,
+        ::R16F, ::RG16F, ::RGB16F, ::RGBA16F, ::R32F, ::RG32F, ::RGB32F, ::RGBA32F,
+        ::R11FG11FB10F, ::R8I, ::RG8I, ::RGB8I, ::RGBA8I, ::R16I, ::RG16I, ::RGB16I, ::RGBA16I,
+        ::R32I, ::RG32I, ::RGB32I, ::RGBA32I, ::R8UI, ::RG8UI, ::RGB8UI, ::RGBA8UI,
+        ::R16UI, ::RG16UI, ::RGB16UI, ::RGBA16UI, ::R32UI, ::RG32UI, ::RGB32UI, ::RGBA32UI,
+        ::SRGB8, ::SRGB8Alpha8, ::Depth16, ::Depth24, ::Depth32F, ::Depth24Stencil8,
+    ];
+
+    #[test]
+    fn format_kind_of_internal_format() {
+        // the kind of an existing texture is found back from its internal format
+        for &format in FORMATS.iter() {
+            let (internal_format, _, kind, _) = get_format_infos(format).unwrap();
+            assert!(get_format_kind(internal_format) == kind, "{}", format);
+        }
+    }
+
+    #[test]
+    fn minimal_versions() {
+        let version = |format| { let (_, _, _, v) = get_format_infos(format).unwrap(); v };
+
+        assert_eq!(version(::RGBA8), (1, 1));
+        assert_eq!(version(::SRGB8Alpha8), (2, 1));
+        assert_eq!(version(::Depth24), (1, 4));
+        assert_eq!(version(::RGBA32F), (3, 0));
+        assert_eq!(version(::R8UI), (3, 0));
+    }
+
+    #[test]
+    fn integer_formats_require_integer_data() {
+        assert_eq!(get_client_format(SignedIntegerFormat, 4, gl::BYTE),
+            Some((gl::RGBA_INTEGER, gl::BYTE)));
+        assert_eq!(get_client_format(UnsignedIntegerFormat, 2, gl::UNSIGNED_INT),
+            Some((gl::RG_INTEGER, gl::UNSIGNED_INT)));
+
+        assert_eq!(get_client_format(SignedIntegerFormat, 4, gl::FLOAT), None);
+        assert_eq!(get_client_format(SignedIntegerFormat, 4, gl::UNSIGNED_BYTE), None);
+        assert_eq!(get_client_format(UnsignedIntegerFormat, 1, gl::INT), None);
+    }
+
+    #[test]
+    fn float_formats_accept_any_data() {
+        assert_eq!(get_client_format(FloatFormat, 3, gl::UNSIGNED_BYTE),
+            Some((gl::RGB, gl::UNSIGNED_BYTE)));
+        assert_eq!(get_client_format(FloatFormat, 1, gl::FLOAT), Some((gl::RED, gl::FLOAT)));
+    }
+
+    #[test]
+    fn depth_formats_client_format() {
+        assert_eq!(get_client_format(DepthFormat, 1, gl::FLOAT),
+            Some((gl::DEPTH_COMPONENT, gl::FLOAT)));
+        assert_eq!(get_client_format(DepthFormat, 2, gl::FLOAT), None);
+
+        assert_eq!(get_client_format(DepthStencilFormat, 1, gl::UNSIGNED_INT),
+            Some((gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)));
+        assert_eq!(get_client_format(DepthStencilFormat, 1, gl::FLOAT), None);
+    }
+}
@@ -0,0 +1,348 @@
+use gl;
+use libc;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+/// A buffer in the graphics card's memory which contains the values of a uniform block.
+///
+/// The content of the buffer is laid out according to the `std140` rules, so the uniform
+/// block must be declared with `layout(std140)` in the shaders.
+///
+/// Uniform buffers are not supported by GLES, where building one fails.
+pub struct UniformBuffer<T> {
+    buffer: Arc<UniformBufferImpl>,
+    layout: UniformBlockLayout,
+    offsets: Vec<uint>,
+}
+
+/// This public function is accessible from within `simple_gl` but not for the user.
+pub fn get_impl<'a, T>(buffer: &'a UniformBuffer<T>) -> &'a Arc<UniformBufferImpl> {
+    &buffer.buffer
+}
+
+/// This public function is accessible from within `simple_gl` but not for the user.
+pub fn get_std140_layout<T>(buffer: &UniformBuffer<T>)
+    -> Vec<(String, gl::types::GLenum, Option<uint>, uint)>
+{
+    buffer.layout.iter().zip(buffer.offsets.iter())
+        .map(|(&(ref name, gltype, array_size, _), &offset)| (name.clone(), gltype, array_size, offset))
+        .collect()
+}
+
+impl<T: UniformBlock + 'static + Send> UniformBuffer<T> {
+    /// Builds a new uniform buffer.
+    ///
+    /// Fields that are GLSL arrays must have the `#[array]` attribute, otherwise a field like
+    /// `[[f32, ..3], ..2]` is a `mat2x3`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # #![feature(phase)]
+    /// # #[phase(plugin)]
+    /// # extern crate simple_gl_macros;
+    /// # extern crate simple_gl;
+    /// # fn main() {
+    /// #[uniform_block]
+    /// struct Camera {
+    ///     view: [[f32, ..4], ..4],
+    ///     position: [f32, ..3],
+    ///     #[array]
+    ///     lights: [[f32, ..4], ..8],
+    /// }
+    ///
+    /// # let display: simple_gl::Display = unsafe { std::mem::uninitialized() };
+    /// # let view = unsafe { std::mem::uninitialized() };
+    /// let buffer = simple_gl::UniformBuffer::new(&display, Camera {
+    ///     view: view,
+    ///     position: [0.0, 1.0, 5.0],
+    ///     lights: [[0.0, ..4], ..8],
+    /// });
+    /// # }
+    /// ```
+    ///
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn new(display: &super::Display, data: T) -> UniformBuffer<T> {
+        let layout = UniformBlock::build_layout(None::<T>);
+        let (offsets, size) = compute_std140_layout(layout.iter().map(|&(_, ty, array_size, _)| (ty, array_size)));
+        let data = write_std140(&layout, offsets.as_slice(), size, &data);
+
+        let id = display.context.context.exec(proc(gl) {
+            unsafe {
+                let mut id: gl::types::GLuint = mem::uninitialized();
+                gl.GenBuffers(1, &mut id);
+                gl.BindBuffer(gl::UNIFORM_BUFFER, id);
+                gl.BufferData(gl::UNIFORM_BUFFER, size as gl::types::GLsizeiptr,
+                    data.as_ptr() as *const libc::c_void, gl::DYNAMIC_DRAW);
+                id
+            }
+        }).get();
+
+        UniformBuffer {
+            buffer: Arc::new(UniformBufferImpl {
+                display: display.context.clone(),
+                id: id,
+                size: size,
+            }),
+            layout: layout,
+            offsets: offsets,
+        }
+    }
+
+    /// Builds a new uniform buffer.
+    #[cfg(target_os = "android")]
+    pub fn new(_: &super::Display, _: T) -> UniformBuffer<T> {
+        fail!("Uniform buffers are not supported by GLES")
+    }
+
+    /// Replaces the content of the buffer.
+    ///
+    /// All the programs that use this buffer will see the new values the next time they are
+    /// drawn.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn upload(&mut self, data: T) {
+        let size = self.buffer.size;
+        let data = write_std140(&self.layout, self.offsets.as_slice(), size, &data);
+        let id = self.buffer.id;
+
+        self.buffer.display.context.exec(proc(gl) {
+            unsafe {
+                gl.BindBuffer(gl::UNIFORM_BUFFER, id);
+                gl.BufferSubData(gl::UNIFORM_BUFFER, 0, size as gl::types::GLsizeiptr,
+                    data.as_ptr() as *const libc::c_void);
+            }
+        });
+    }
+
+    /// Replaces the content of the buffer.
+    #[cfg(target_os = "android")]
+    pub fn upload(&mut self, _: T) {
+        fail!("Uniform buffers are not supported by GLES")
+    }
+}
+
+impl<T> fmt::Show for UniformBuffer<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+        (format!("UniformBuffer #{} (size: {})", self.buffer.id, self.buffer.size)).fmt(formatter)
+    }
+}
+
+pub struct UniformBufferImpl {
+    display: Arc<super::DisplayImpl>,
+    pub id: gl::types::GLuint,
+    pub size: uint,
+}
+
+impl Drop for UniformBufferImpl {
+    fn drop(&mut self) {
+        let id = self.id.clone();
+        self.display.context.exec(proc(gl) {
+            unsafe { gl.DeleteBuffers(1, [ id ].as_ptr()); }
+        });
+    }
+}
+
+/// For each member of the block, its name, its GLSL type, its number of array elements (`None`
+/// if the member is not an array) and its offset in the Rust structure.
+#[doc(hidden)]
+pub type UniformBlockLayout = Vec<(String, gl::types::GLenum, Option<uint>, uint)>;
+
+/// Trait for structures that represent the content of a uniform block.
+#[doc(hidden)]
+pub trait UniformBlock: Copy {
+    fn build_layout(Option<Self>) -> UniformBlockLayout;
+}
+
+/// Computes the `std140` offset of each member of a block, and the total size of the block.
+///
+/// Each member is described by its GLSL type and its number of array elements, or `None` if it
+/// is not an array.
+pub fn compute_std140_layout<I: Iterator<(gl::types::GLenum, Option<uint>)>>(mut members: I)
+    -> (Vec<uint>, uint)
+{
+    let mut offsets = Vec::new();
+    let mut current_offset = 0;
+
+    for (gltype, array_size) in members {
+        let (components, columns, _) = get_type_shape(gltype);
+
+        // vectors of 3 components are aligned like vectors of 4 components, and matrices
+        //  and arrays (even of one element) are stored like arrays of `vec4`s
+        let (alignment, size) = match array_size {
+            Some(array_size) => (16, 16 * columns * array_size),
+            None if columns > 1 => (16, 16 * columns),
+            None => match components {
+                1 => (4, 4),
+                2 => (8, 8),
+                3 => (16, 12),
+                _ => (16, 16),
+            }
+        };
+
+        current_offset = round_up(current_offset, alignment);
+        offsets.push(current_offset);
+        current_offset += size;
+    }
+
+    (offsets, round_up(current_offset, 16))
+}
+
+/// Copies the content of a block into a buffer with the `std140` layout.
+fn write_std140<T>(layout: &UniformBlockLayout, offsets: &[uint], size: uint, data: &T) -> Vec<u8> {
+    let mut buffer = Vec::from_elem(size, 0u8);
+    let source = data as *const T as *const u8;
+
+    for (&(_, gltype, array_size, source_offset), &offset) in layout.iter().zip(offsets.iter()) {
+        let (components, columns, is_bool) = get_type_shape(gltype);
+
+        // each array element starts on a multiple of 16 bytes in the buffer, while the elements
+        //  of a Rust array are packed
+        let destination_stride = 16 * columns;
+        let source_stride = components * columns;
+
+        for element in range(0, array_size.unwrap_or(1)) {
+            for column in range(0, columns) {
+                for component in range(0, components) {
+                    let index = element * source_stride + column * components + component;
+                    let destination = offset + element * destination_stride + column * 16 +
+                                      component * 4;
+
+                    unsafe {
+                        // booleans are one byte in Rust and four bytes in GLSL
+                        let value: u32 = if is_bool {
+                            if *source.offset((source_offset + index) as int) != 0 { 1 } else { 0 }
+                        } else {
+                            *(source.offset((source_offset + index * 4) as int) as *const u32)
+                        };
+
+                        *(buffer.as_mut_ptr().offset(destination as int) as *mut u32) = value;
+                    }
+                }
+            }
+        }
+    }
+
+    buffer
+}
+
+/// Returns the number of components per column, the number of columns, and whether the
+/// components are booleans.
+fn get_type_shape(gltype: gl::types::GLenum) -> (uint, uint, bool) {
+    match gltype {
+        gl::FLOAT | gl::INT => (1, 1, false),
+        gl::FLOAT_VEC2 | gl::INT_VEC2 => (2, 1, false),
+        gl::FLOAT_VEC3 | gl::INT_VEC3 => (3, 1, false),
+        gl::FLOAT_VEC4 | gl::INT_VEC4 => (4, 1, false),
+        gl::BOOL => (1, 1, true),
+        gl::BOOL_VEC2 => (2, 1, true),
+        gl::BOOL_VEC3 => (3, 1, true),
+        gl::BOOL_VEC4 => (4, 1, true),
+        gl::FLOAT_MAT2 => (2, 2, false),
+        gl::FLOAT_MAT3 => (3, 3, false),
+        gl::FLOAT_MAT4 => (4, 4, false),
+        _ => get_desktop_type_shape(gltype)
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_desktop_type_shape(gltype: gl::types::GLenum) -> (uint, uint, bool) {
+    match gltype {
+        gl::UNSIGNED_INT => (1, 1, false),
+        gl::UNSIGNED_INT_VEC2 => (2, 1, false),
+        gl::UNSIGNED_INT_VEC3 => (3, 1, false),
+        gl::UNSIGNED_INT_VEC4 => (4, 1, false),
+        gl::FLOAT_MAT2x3 => (3, 2, false),
+        gl::FLOAT_MAT2x4 => (4, 2, false),
+        gl::FLOAT_MAT3x2 => (2, 3, false),
+        gl::FLOAT_MAT3x4 => (4, 3, false),
+        gl::FLOAT_MAT4x2 => (2, 4, false),
+        gl::FLOAT_MAT4x3 => (3, 4, false),
+        _ => fail!("This type is not supported in uniform blocks")
+    }
+}
+
+#[cfg(target_os = "android")]
+fn get_desktop_type_shape(_: gl::types::GLenum) -> (uint, uint, bool) {
+    fail!("This type is not supported in uniform blocks")
+}
+
+fn round_up(value: uint, alignment: uint) -> uint {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use gl;
+    use super::compute_std140_layout;
+
+    #[test]
+    fn vec3_followed_by_float() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT_VEC3, None), (gl::FLOAT, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 12]);
+        assert_eq!(size, 16);
+    }
+
+    #[test]
+    fn float_followed_by_vec2() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT, None), (gl::FLOAT_VEC2, None), (gl::FLOAT_VEC3, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 8, 16]);
+        assert_eq!(size, 32);
+    }
+
+    #[test]
+    fn float_array() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT, Some(3)), (gl::FLOAT, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 48]);
+        assert_eq!(size, 64);
+    }
+
+    #[test]
+    fn array_of_one_element() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT, Some(1)), (gl::FLOAT, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 16]);
+        assert_eq!(size, 32);
+    }
+
+    #[test]
+    fn mat3() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT, None), (gl::FLOAT_MAT3, None), (gl::FLOAT, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 16, 64]);
+        assert_eq!(size, 80);
+    }
+
+    #[test]
+    fn non_square_matrices() {
+        // `mat2x3` has 2 columns of 3 components, `mat3x2` has 3 columns of 2 components
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT_MAT2x3, None), (gl::FLOAT_MAT3x2, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 32]);
+        assert_eq!(size, 80);
+    }
+
+    #[test]
+    fn array_of_matrices() {
+        let (offsets, size) = compute_std140_layout(
+            vec![(gl::FLOAT_MAT2, Some(2)), (gl::FLOAT, None)].move_iter());
+
+        assert_eq!(offsets, vec![0, 64]);
+        assert_eq!(size, 80);
+    }
+}
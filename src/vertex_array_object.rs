@@ -0,0 +1,408 @@
+use gl;
+use libc;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+use vertex_buffer::VertexBindings;
+
+/// For each attribute of a program, its location, type and size.
+pub type ProgramAttributes = HashMap<String, (gl::types::GLint, gl::types::GLenum, gl::types::GLint)>;
+
+/// A vertex buffer to bind, with its elements size, its bindings and its divisor.
+///
+/// A divisor of `0` means one element per vertex, and `1` one element per instance.
+pub type VertexBufferBinding = (gl::types::GLuint, uint, VertexBindings, gl::types::GLuint);
+
+/// The program, vertex buffers and index buffer (or `0`) that a vertex array object binds
+/// together.
+pub type VertexArrayObjectKey = (gl::types::GLuint, Vec<gl::types::GLuint>, gl::types::GLuint);
+
+/// Returns the vertex array object that binds these buffers to the attributes of the
+/// program, building it if it doesn't exist yet.
+///
+/// The buffers are checked with `check_attributes` before the vertex array object is built.
+/// Since the bindings of a buffer never change, existing vertex array objects don't need to be
+/// checked again.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn get_vertex_array_object(display: &Arc<super::DisplayImpl>, program: gl::types::GLuint,
+                               attributes: &Arc<ProgramAttributes>,
+                               vertex_buffers: &Vec<VertexBufferBinding>,
+                               index_buffer: gl::types::GLuint)
+    -> Result<gl::types::GLuint, super::AttributeError>
+{
+    let key = (program, vertex_buffers.iter().map(|&(id, _, _, _)| id).collect(), index_buffer);
+
+    // the lock is not held while waiting for the vertex array object to be built, so that the
+    //  other threads don't have to wait for the commands that are already queued
+    match display.vertex_array_objects.lock().find(&key) {
+        Some(&id) => return Ok(id),
+        None => ()
+    }
+
+    try!(check_attributes(&**attributes, vertex_buffers));
+
+    let attributes = attributes.clone();
+    let vertex_buffers = vertex_buffers.clone();
+
+    let id = display.context.exec(proc(gl) {
+        unsafe {
+            let mut id: gl::types::GLuint = mem::uninitialized();
+            gl.GenVertexArrays(1, &mut id);
+            gl.BindVertexArray(id);
+
+            bind_attributes(gl, &*attributes, &vertex_buffers);
+            if index_buffer != 0 {
+                gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+            }
+
+            // unbinding so that the buffers functions don't modify the vertex array object
+            gl.BindVertexArray(0);
+            id
+        }
+    }).get();
+
+    // another thread may have built the same vertex array object in the meantime
+    let mut vertex_array_objects = display.vertex_array_objects.lock();
+    match vertex_array_objects.find(&key) {
+        Some(&existing) => {
+            display.context.exec(proc(gl) {
+                unsafe { gl.DeleteVertexArrays(1, [ id ].as_ptr()); }
+            });
+            return Ok(existing);
+        },
+        None => ()
+    }
+
+    vertex_array_objects.insert(key, id);
+    Ok(id)
+}
+
+/// Vertex array objects are not supported by GLES, so the attributes are checked and bound
+/// at each draw.
+#[cfg(target_os = "android")]
+pub fn get_vertex_array_object(_: &Arc<super::DisplayImpl>, _: gl::types::GLuint,
+                               attributes: &Arc<ProgramAttributes>,
+                               vertex_buffers: &Vec<VertexBufferBinding>,
+                               _: gl::types::GLuint)
+    -> Result<gl::types::GLuint, super::AttributeError>
+{
+    try!(check_attributes(&**attributes, vertex_buffers));
+    Ok(0)
+}
+
+/// Checks that the vertex buffers provide all the attributes of the program, with a type
+/// that matches the type in the shader.
+///
+/// Elements of the vertex buffers that are not used by the program are ignored, since the
+/// GLSL compiler removes the attributes that are not used by the shaders. Elements can have
+/// fewer components than the attribute, in which case the missing ones are filled by OpenGL,
+/// except for matrices whose elements must contain every column.
+pub fn check_attributes(attributes: &ProgramAttributes, vertex_buffers: &Vec<VertexBufferBinding>)
+    -> Result<(), super::AttributeError>
+{
+    for (name, &(_, attribute_type, _)) in attributes.iter() {
+        let binding = vertex_buffers.iter()
+            .filter_map(|&(_, _, ref bindings, _)| bindings.find(name)).next();
+
+        let &(data_type, data_size, _, normalize) = match binding {
+            Some(binding) => binding,
+            None => return Err(super::AttributeMissing(name.clone()))
+        };
+
+        let (component_type, prefix) = get_binding_component_type(data_type, normalize);
+
+        let compatible = match get_attribute_type_infos(attribute_type) {
+            Some((expected_type, components, 1)) =>
+                expected_type == component_type && data_size <= components,
+            Some((expected_type, components, columns)) =>
+                expected_type == component_type && data_size == components * columns,
+            None => false
+        };
+
+        if !compatible {
+            return Err(super::AttributeTypeMismatch(name.clone(),
+                super::get_glsl_type_name(attribute_type),
+                get_binding_type_name(prefix, data_size)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Binds a vertex array object returned by `get_vertex_array_object`.
+///
+/// Returns the list of locations that must be passed to `unbind_vertex_array_object`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn bind_vertex_array_object(gl: &gl::Gl, id: gl::types::GLuint, _: &ProgramAttributes,
+                                _: &Vec<VertexBufferBinding>, _: gl::types::GLuint)
+    -> Vec<(gl::types::GLuint, gl::types::GLuint)>
+{
+    gl.BindVertexArray(id);
+    Vec::new()
+}
+
+/// Binds the attributes and the index buffer directly, since GLES doesn't have vertex
+/// array objects.
+///
+/// Returns the list of locations that must be passed to `unbind_vertex_array_object`.
+#[cfg(target_os = "android")]
+pub fn bind_vertex_array_object(gl: &gl::Gl, _: gl::types::GLuint, attributes: &ProgramAttributes,
+                                vertex_buffers: &Vec<VertexBufferBinding>,
+                                index_buffer: gl::types::GLuint)
+    -> Vec<(gl::types::GLuint, gl::types::GLuint)>
+{
+    let locations = bind_attributes(gl, attributes, vertex_buffers);
+    if index_buffer != 0 {
+        gl.BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer);
+    }
+    locations
+}
+
+/// Restores the state that was modified by `bind_vertex_array_object`.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+pub fn unbind_vertex_array_object(gl: &gl::Gl, _: &[(gl::types::GLuint, gl::types::GLuint)]) {
+    gl.BindVertexArray(0);
+}
+
+/// Restores the state that was modified by `bind_vertex_array_object`.
+#[cfg(target_os = "android")]
+pub fn unbind_vertex_array_object(gl: &gl::Gl, locations: &[(gl::types::GLuint, gl::types::GLuint)]) {
+    for &(location, _) in locations.iter() {
+        gl.DisableVertexAttribArray(location);
+    }
+}
+
+/// Destroys the vertex array objects that use a buffer. Must be called when the buffer
+/// is destroyed.
+pub fn purge_buffer(display: &Arc<super::DisplayImpl>, buffer: gl::types::GLuint) {
+    purge(display, |&(_, ref vertex_buffers, index_buffer)| {
+        index_buffer == buffer || vertex_buffers.contains(&buffer)
+    });
+}
+
+/// Destroys the vertex array objects that use a program. Must be called when the program
+/// is destroyed.
+pub fn purge_program(display: &Arc<super::DisplayImpl>, program: gl::types::GLuint) {
+    purge(display, |&(p, _, _)| p == program);
+}
+
+fn purge(display: &Arc<super::DisplayImpl>, filter: |&VertexArrayObjectKey| -> bool) {
+    let mut vertex_array_objects = display.vertex_array_objects.lock();
+
+    let keys: Vec<VertexArrayObjectKey> = vertex_array_objects.keys()
+        .filter(|k| filter(*k)).map(|k| k.clone()).collect();
+
+    if keys.len() == 0 {
+        return;
+    }
+
+    let ids: Vec<gl::types::GLuint> = keys.iter()
+        .map(|k| vertex_array_objects.pop(k).unwrap()).collect();
+
+    display.context.exec(proc(gl) {
+        delete_vertex_array_objects(gl, ids.as_slice());
+    });
+}
+
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn delete_vertex_array_objects(gl: &gl::Gl, ids: &[gl::types::GLuint]) {
+    unsafe { gl.DeleteVertexArrays(ids.len() as gl::types::GLsizei, ids.as_ptr()); }
+}
+
+#[cfg(target_os = "android")]
+fn delete_vertex_array_objects(_: &gl::Gl, _: &[gl::types::GLuint]) {
+}
+
+/// Binds the attributes of vertex buffers to the locations of the program, and returns the
+/// locations that have been enabled with their divisor.
+///
+/// Attributes that are not used by the program are ignored. Matrices use one location for each
+/// of their columns.
+fn bind_attributes(gl: &gl::Gl, attributes: &ProgramAttributes,
+                   vertex_buffers: &Vec<VertexBufferBinding>)
+    -> Vec<(gl::types::GLuint, gl::types::GLuint)>
+{
+    let mut locations = Vec::new();
+
+    for &(buffer_id, elements_size, ref bindings, divisor) in vertex_buffers.iter() {
+        gl.BindBuffer(gl::ARRAY_BUFFER, buffer_id);
+
+        for (name, &(data_type, data_size, data_offset, normalize)) in bindings.iter() {
+            let (loc, attribute_type) = match attributes.find(name) {
+                Some(&(loc, ty, _)) => (loc as gl::types::GLuint, ty),
+                None => continue
+            };
+
+            // the columns of a matrix are at consecutive locations, and matrices are always
+            //  made of floats
+            let (data_size, columns) = match get_attribute_type_infos(attribute_type) {
+                Some((_, components, columns)) if columns > 1 => (components, columns),
+                _ => (data_size, 1)
+            };
+            let column_size = data_size as uint * mem::size_of::<f32>();
+
+            for column in range(0, columns) {
+                let loc = loc + column as gl::types::GLuint;
+
+                vertex_attrib_pointer(gl, loc, data_type, data_size, normalize,
+                    elements_size as gl::types::GLsizei, data_offset + column as uint * column_size);
+
+                gl.EnableVertexAttribArray(loc);
+
+                if divisor != 0 {
+                    super::vertex_attrib_divisor(gl, loc, divisor);
+                }
+
+                locations.push((loc, divisor));
+            }
+        }
+    }
+
+    locations
+}
+
+/// Calls the right `VertexAttrib*Pointer` function depending on the type of the attribute.
+///
+/// Integers that are not normalized are passed with `VertexAttribIPointer`, so that they can be
+/// read as `int` or `uint` in the shader. Doubles are passed with `VertexAttribLPointer`, which
+/// requires OpenGL 4.1 or the `GL_ARB_vertex_attrib_64bit` extension.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn vertex_attrib_pointer(gl: &gl::Gl, location: gl::types::GLuint, data_type: gl::types::GLenum,
+                         data_size: gl::types::GLint, normalize: bool,
+                         stride: gl::types::GLsizei, offset: uint)
+{
+    let offset = offset as *const libc::c_void;
+
+    unsafe {
+        match data_type {
+            gl::BYTE | gl::UNSIGNED_BYTE | gl::SHORT | gl::UNSIGNED_SHORT | gl::INT |
+            gl::UNSIGNED_INT if !normalize
+                => gl.VertexAttribIPointer(location, data_size, data_type, stride, offset),
+            gl::DOUBLE
+                => gl.VertexAttribLPointer(location, data_size, data_type, stride, offset),
+            _ => gl.VertexAttribPointer(location, data_size, data_type,
+                    if normalize { gl::TRUE } else { gl::FALSE }, stride, offset)
+        }
+    }
+}
+
+/// Calls `VertexAttribPointer`.
+///
+/// GLES 2 has no integer attributes, so integers are converted to floats by the GPU.
+#[cfg(target_os = "android")]
+fn vertex_attrib_pointer(gl: &gl::Gl, location: gl::types::GLuint, data_type: gl::types::GLenum,
+                         data_size: gl::types::GLint, normalize: bool,
+                         stride: gl::types::GLsizei, offset: uint)
+{
+    match data_type {
+        gl::INT | gl::UNSIGNED_INT => fail!("32 bits integer attributes are not supported by GLES"),
+        _ => ()
+    }
+
+    unsafe {
+        gl.VertexAttribPointer(location, data_size, data_type,
+            if normalize { gl::TRUE } else { gl::FALSE }, stride, offset as *const libc::c_void)
+    }
+}
+
+/// Returns the type of the components of a GLSL attribute type, their number per column and
+/// the number of columns, or `None` if the type can't be fed by a vertex buffer.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_attribute_type_infos(gltype: gl::types::GLenum)
+    -> Option<(gl::types::GLenum, gl::types::GLint, gl::types::GLint)>
+{
+    match gltype {
+        gl::FLOAT => Some((gl::FLOAT, 1, 1)),
+        gl::FLOAT_VEC2 => Some((gl::FLOAT, 2, 1)),
+        gl::FLOAT_VEC3 => Some((gl::FLOAT, 3, 1)),
+        gl::FLOAT_VEC4 => Some((gl::FLOAT, 4, 1)),
+        gl::INT => Some((gl::INT, 1, 1)),
+        gl::INT_VEC2 => Some((gl::INT, 2, 1)),
+        gl::INT_VEC3 => Some((gl::INT, 3, 1)),
+        gl::INT_VEC4 => Some((gl::INT, 4, 1)),
+        gl::UNSIGNED_INT => Some((gl::UNSIGNED_INT, 1, 1)),
+        gl::UNSIGNED_INT_VEC2 => Some((gl::UNSIGNED_INT, 2, 1)),
+        gl::UNSIGNED_INT_VEC3 => Some((gl::UNSIGNED_INT, 3, 1)),
+        gl::UNSIGNED_INT_VEC4 => Some((gl::UNSIGNED_INT, 4, 1)),
+        gl::DOUBLE => Some((gl::DOUBLE, 1, 1)),
+        gl::DOUBLE_VEC2 => Some((gl::DOUBLE, 2, 1)),
+        gl::DOUBLE_VEC3 => Some((gl::DOUBLE, 3, 1)),
+        gl::DOUBLE_VEC4 => Some((gl::DOUBLE, 4, 1)),
+        gl::FLOAT_MAT2 => Some((gl::FLOAT, 2, 2)),
+        gl::FLOAT_MAT3 => Some((gl::FLOAT, 3, 3)),
+        gl::FLOAT_MAT4 => Some((gl::FLOAT, 4, 4)),
+        gl::FLOAT_MAT2x3 => Some((gl::FLOAT, 3, 2)),
+        gl::FLOAT_MAT2x4 => Some((gl::FLOAT, 4, 2)),
+        gl::FLOAT_MAT3x2 => Some((gl::FLOAT, 2, 3)),
+        gl::FLOAT_MAT3x4 => Some((gl::FLOAT, 4, 3)),
+        gl::FLOAT_MAT4x2 => Some((gl::FLOAT, 2, 4)),
+        gl::FLOAT_MAT4x3 => Some((gl::FLOAT, 3, 4)),
+        _ => None
+    }
+}
+
+/// Returns the type of the components of a GLSL attribute type, their number per column and
+/// the number of columns, or `None` if the type can't be fed by a vertex buffer.
+#[cfg(target_os = "android")]
+fn get_attribute_type_infos(gltype: gl::types::GLenum)
+    -> Option<(gl::types::GLenum, gl::types::GLint, gl::types::GLint)>
+{
+    match gltype {
+        gl::FLOAT => Some((gl::FLOAT, 1, 1)),
+        gl::FLOAT_VEC2 => Some((gl::FLOAT, 2, 1)),
+        gl::FLOAT_VEC3 => Some((gl::FLOAT, 3, 1)),
+        gl::FLOAT_VEC4 => Some((gl::FLOAT, 4, 1)),
+        gl::FLOAT_MAT2 => Some((gl::FLOAT, 2, 2)),
+        gl::FLOAT_MAT3 => Some((gl::FLOAT, 3, 3)),
+        gl::FLOAT_MAT4 => Some((gl::FLOAT, 4, 4)),
+        _ => None
+    }
+}
+
+/// Returns the type of the components as seen by the shader when an element of a vertex buffer
+/// is bound with `vertex_attrib_pointer`, and the prefix of the GLSL vector types.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn get_binding_component_type(data_type: gl::types::GLenum, normalize: bool)
+    -> (gl::types::GLenum, &'static str)
+{
+    match data_type {
+        gl::BYTE | gl::SHORT | gl::INT if !normalize => (gl::INT, "i"),
+        gl::UNSIGNED_BYTE | gl::UNSIGNED_SHORT | gl::UNSIGNED_INT if !normalize
+            => (gl::UNSIGNED_INT, "u"),
+        gl::DOUBLE => (gl::DOUBLE, "d"),
+        _ => (gl::FLOAT, "")
+    }
+}
+
+/// Returns the type of the components as seen by the shader when an element of a vertex buffer
+/// is bound with `vertex_attrib_pointer`, and the prefix of the GLSL vector types.
+#[cfg(target_os = "android")]
+fn get_binding_component_type(_: gl::types::GLenum, _: bool) -> (gl::types::GLenum, &'static str) {
+    (gl::FLOAT, "")
+}
+
+/// Returns the name of the GLSL type that matches an element of a vertex buffer, for error
+/// messages.
+fn get_binding_type_name(prefix: &str, size: gl::types::GLint) -> String {
+    match (prefix, size) {
+        ("", 1) => "float".to_string(),
+        ("i", 1) => "int".to_string(),
+        ("u", 1) => "uint".to_string(),
+        ("d", 1) => "double".to_string(),
+        (prefix, size) => format!("{}vec{}", prefix, size)
+    }
+}
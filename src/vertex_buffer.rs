@@ -2,14 +2,20 @@ use gl;
 use libc;
 use std::collections::HashMap;
 use std::fmt;
+use std::kinds::marker;
 use std::mem;
+use std::ptr;
+use std::raw;
 use std::sync::Arc;
+use vertex_array_object;
 
 /// A list of verices loaded in the graphics card's memory.
 pub struct VertexBuffer<T> {
     display: Arc<super::DisplayImpl>,
     id: gl::types::GLuint,
     elements_size: uint,
+    elements_count: uint,
+    usage: gl::types::GLenum,
     bindings: VertexBindings,
 }
 
@@ -44,10 +50,22 @@ impl<T: VertexFormat + 'static + Send> VertexBuffer<T> {
     /// ```
     /// 
     pub fn new(display: &super::Display, data: Vec<T>) -> VertexBuffer<T> {
+        VertexBuffer::new_with_usage(display, data, super::StaticDraw)
+    }
+
+    /// Builds a new vertex buffer with the given usage hint.
+    ///
+    /// Use `DynamicDraw` or `StreamDraw` if you are going to modify the content of the
+    /// buffer with `write` or `map`.
+    pub fn new_with_usage(display: &super::Display, data: Vec<T>, usage: super::BufferUsage)
+        -> VertexBuffer<T>
+    {
         let bindings = VertexFormat::build_bindings(None::<T>);
 
         let elements_size = { use std::mem; mem::size_of::<T>() };
-        let buffer_size = data.len() * elements_size as uint;
+        let elements_count = data.len();
+        let buffer_size = elements_count * elements_size as uint;
+        let usage = usage.get_gl_enum();
 
         let id = display.context.context.exec(proc(gl) {
             unsafe {
@@ -55,7 +73,7 @@ impl<T: VertexFormat + 'static + Send> VertexBuffer<T> {
                 gl.GenBuffers(1, &mut id);
                 gl.BindBuffer(gl::ARRAY_BUFFER, id);
                 gl.BufferData(gl::ARRAY_BUFFER, buffer_size as gl::types::GLsizeiptr,
-                    data.as_ptr() as *const libc::c_void, gl::STATIC_DRAW);
+                    data.as_ptr() as *const libc::c_void, usage);
                 id
             }
         }).get();
@@ -64,9 +82,263 @@ impl<T: VertexFormat + 'static + Send> VertexBuffer<T> {
             display: display.context.clone(),
             id: id,
             elements_size: elements_size,
+            elements_count: elements_count,
+            usage: usage,
             bindings: bindings
         }
     }
+
+    /// Returns the number of vertices in the buffer.
+    pub fn len(&self) -> uint {
+        self.elements_count
+    }
+
+    /// Replaces some vertices of the buffer, starting at the vertex of index `offset`.
+    ///
+    /// Fails if `offset + data.len()` is greater than the number of vertices in the buffer.
+    pub fn write(&mut self, offset: uint, data: &[T]) {
+        if offset + data.len() > self.elements_count {
+            fail!("Writing {} vertices at offset {} in a buffer of {} vertices", data.len(),
+                offset, self.elements_count);
+        }
+
+        let id = self.id;
+        let offset = offset * self.elements_size;
+        let size = data.len() * self.elements_size;
+        let data_ptr = data.as_ptr() as *const libc::c_void;
+
+        self.display.context.exec(proc(gl) {
+            unsafe {
+                gl.BindBuffer(gl::ARRAY_BUFFER, id);
+                gl.BufferSubData(gl::ARRAY_BUFFER, offset as gl::types::GLintptr,
+                    size as gl::types::GLsizeiptr, data_ptr);
+            }
+        }).get();
+    }
+
+    /// Reads the content of the buffer.
+    ///
+    /// This function waits for all the pending draws that use the buffer to be finished,
+    /// so it is slow and should only be used for debugging or for reading data written by
+    /// the GPU.
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn read(&self) -> Vec<T> {
+        let id = self.id;
+        let elements_count = self.elements_count;
+        let size = elements_count * self.elements_size;
+
+        self.display.context.exec(proc(gl) {
+            let mut data: Vec<T> = Vec::with_capacity(elements_count);
+
+            unsafe {
+                gl.BindBuffer(gl::ARRAY_BUFFER, id);
+                gl.GetBufferSubData(gl::ARRAY_BUFFER, 0, size as gl::types::GLsizeiptr,
+                    data.as_mut_ptr() as *mut libc::c_void);
+                data.set_len(elements_count);
+            }
+
+            data
+        }).get()
+    }
+
+    /// Reads the content of the buffer.
+    #[cfg(target_os = "android")]
+    pub fn read(&self) -> Vec<T> {
+        fail!("Reading buffers is not supported by GLES")
+    }
+
+    /// Maps the buffer in memory in order to replace its content.
+    ///
+    /// The buffer is orphaned first: the GPU can keep using the old content for the draws
+    /// that are still pending, while you write the new content. This means that **the
+    /// mapping doesn't contain the previous content** but zeroes, and that you must write
+    /// every vertex. The buffer is unmapped by `Mapping::finish` or when the `Mapping` is
+    /// destroyed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # let mut vertex_buffer: simple_gl::VertexBuffer<[f32, ..2]> =
+    /// #     unsafe { std::mem::uninitialized() };
+    /// let mut mapping = vertex_buffer.map();
+    /// for (i, vertex) in mapping.mut_iter().enumerate() {
+    ///     *vertex = [i as f32, 0.0];
+    /// }
+    /// mapping.finish().unwrap();
+    /// ```
+    ///
+    #[cfg(target_os = "windows")]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "macos")]
+    pub fn map<'a>(&'a mut self) -> Mapping<'a, T> {
+        let id = self.id;
+        let size = self.elements_count * self.elements_size;
+        let usage = self.usage;
+
+        // mapping an empty range is an error
+        if size == 0 {
+            let mut temporary = Vec::new();
+            return Mapping {
+                data: temporary.as_mut_ptr(),
+                buffer: self,
+                temporary: Some(temporary),
+                unmapped: false,
+            };
+        }
+
+        // the mapping can be read through the `Mapping`, so it can't be write-only ; the
+        //  buffer is orphaned with `BufferData` instead of `MAP_INVALIDATE_BUFFER_BIT`, which
+        //  is forbidden in read mappings
+        let data = self.display.context.exec(proc(gl) {
+            unsafe {
+                gl.BindBuffer(gl::ARRAY_BUFFER, id);
+                gl.BufferData(gl::ARRAY_BUFFER, size as gl::types::GLsizeiptr, ptr::null(), usage);
+                gl.MapBufferRange(gl::ARRAY_BUFFER, 0, size as gl::types::GLsizeiptr,
+                    gl::MAP_READ_BIT | gl::MAP_WRITE_BIT) as *mut T
+            }
+        }).get();
+
+        if data.is_null() {
+            fail!("Failed to map the vertex buffer");
+        }
+
+        // the content of an orphaned buffer is undefined
+        unsafe { ptr::zero_memory(data, self.elements_count); }
+
+        Mapping {
+            buffer: self,
+            data: data,
+            temporary: None,
+            unmapped: false,
+        }
+    }
+
+    /// Maps the buffer in memory in order to replace its content.
+    ///
+    /// GLES doesn't support mapping buffers, so the content is written in a temporary
+    /// buffer filled with zeroes which is uploaded by `Mapping::finish` or when the `Mapping`
+    /// is destroyed.
+    #[cfg(target_os = "android")]
+    pub fn map<'a>(&'a mut self) -> Mapping<'a, T> {
+        let mut temporary = Vec::with_capacity(self.elements_count);
+        unsafe {
+            ptr::zero_memory(temporary.as_mut_ptr(), self.elements_count);
+            temporary.set_len(self.elements_count);
+        }
+
+        Mapping {
+            data: temporary.as_mut_ptr(),
+            buffer: self,
+            temporary: Some(temporary),
+            unmapped: false,
+        }
+    }
+}
+
+/// A mapping of a vertex buffer in memory, returned by `VertexBuffer::map`.
+///
+/// The buffer is unmapped by `finish` or when this object is destroyed. The content of the
+/// mapping is accessed by dereferencing it as a slice.
+pub struct Mapping<'a, T> {
+    buffer: &'a mut VertexBuffer<T>,
+    data: *mut T,
+    temporary: Option<Vec<T>>,      // content of the buffer if it is empty or on platforms without mapping
+    unmapped: bool,
+}
+
+impl<'a, T> Mapping<'a, T> {
+    /// Unmaps the buffer.
+    ///
+    /// Returns an error if the content of the buffer has been lost while it was mapped, for
+    /// example because the screen mode changed, in which case the buffer must be written
+    /// again. This error is ignored if the `Mapping` is destroyed without calling this function.
+    pub fn finish(mut self) -> Result<(), super::UnmapError> {
+        unmap(&mut self)
+    }
+
+    /// Returns the content of the mapping.
+    pub fn as_slice<'b>(&'b self) -> &'b [T] {
+        unsafe {
+            mem::transmute(raw::Slice { data: self.data as *const T, len: self.buffer.elements_count })
+        }
+    }
+
+    /// Returns the content of the mapping.
+    pub fn as_mut_slice<'b>(&'b mut self) -> &'b mut [T] {
+        unsafe {
+            mem::transmute(raw::Slice { data: self.data as *const T, len: self.buffer.elements_count })
+        }
+    }
+}
+
+impl<'a, T> Deref<[T]> for Mapping<'a, T> {
+    fn deref<'b>(&'b self) -> &'b [T] {
+        self.as_slice()
+    }
+}
+
+impl<'a, T> DerefMut<[T]> for Mapping<'a, T> {
+    fn deref_mut<'b>(&'b mut self) -> &'b mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+#[unsafe_destructor]
+impl<'a, T> Drop for Mapping<'a, T> {
+    fn drop(&mut self) {
+        let _ = unmap(self);
+    }
+}
+
+/// Unmaps the buffer of a mapping.
+#[cfg(target_os = "windows")]
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "macos")]
+fn unmap<T>(mapping: &mut Mapping<T>) -> Result<(), super::UnmapError> {
+    // the buffer is only unmapped once, and empty buffers are not mapped
+    if mapping.unmapped || mapping.temporary.is_some() {
+        return Ok(());
+    }
+    mapping.unmapped = true;
+
+    let id = mapping.buffer.id;
+    let result = mapping.buffer.display.context.exec(proc(gl) {
+        gl.BindBuffer(gl::ARRAY_BUFFER, id);
+        gl.UnmapBuffer(gl::ARRAY_BUFFER)
+    }).get();
+
+    // the content of the buffer has been lost, for example because the screen mode changed
+    if result == gl::FALSE {
+        return Err(super::BufferContentLost);
+    }
+
+    Ok(())
+}
+
+/// Uploads the temporary content of a mapping.
+#[cfg(target_os = "android")]
+fn unmap<T>(mapping: &mut Mapping<T>) -> Result<(), super::UnmapError> {
+    if mapping.unmapped {
+        return Ok(());
+    }
+    mapping.unmapped = true;
+
+    let id = mapping.buffer.id;
+    let size = mapping.buffer.elements_count * mapping.buffer.elements_size;
+    let usage = mapping.buffer.usage;
+    let data_ptr = mapping.data as *const libc::c_void;
+
+    // waiting for the upload to finish, since `data_ptr` points to `mapping.temporary`
+    mapping.buffer.display.context.exec(proc(gl) {
+        unsafe {
+            gl.BindBuffer(gl::ARRAY_BUFFER, id);
+            gl.BufferData(gl::ARRAY_BUFFER, size as gl::types::GLsizeiptr, data_ptr, usage);
+        }
+    }).get();
+
+    Ok(())
 }
 
 impl<T> fmt::Show for VertexBuffer<T> {
@@ -78,6 +350,8 @@ impl<T> fmt::Show for VertexBuffer<T> {
 #[unsafe_destructor]
 impl<T> Drop for VertexBuffer<T> {
     fn drop(&mut self) {
+        vertex_array_object::purge_buffer(&self.display, self.id);
+
         let id = self.id.clone();
         self.display.context.exec(proc(gl) {
             unsafe { gl.DeleteBuffers(1, [ id ].as_ptr()); }
@@ -85,10 +359,78 @@ impl<T> Drop for VertexBuffer<T> {
     }
 }
 
-/// For each binding, the data type, number of elements, and offset.
+/// The number of instances to draw, and the vertex buffers that contain the attributes of
+/// each instance.
+///
+/// Each element of a per-instance buffer is used by one instance. Instances can also be
+/// distinguished with `gl_InstanceID` in the vertex shader.
+///
+/// # Example
+///
+/// ```no_run
+/// # let vertex_buffer: simple_gl::VertexBuffer<u8> = unsafe { std::mem::uninitialized() };
+/// # let trees_positions: simple_gl::VertexBuffer<u8> = unsafe { std::mem::uninitialized() };
+/// # let index_buffer: simple_gl::IndexBuffer = unsafe { std::mem::uninitialized() };
+/// # let uniforms: simple_gl::ProgramUniforms = unsafe { std::mem::uninitialized() };
+/// # let mut target: simple_gl::Target = unsafe { std::mem::uninitialized() };
+/// // drawing one tree at each position of `trees_positions`
+/// let instances = simple_gl::InstancesList::from_buffer(&trees_positions);
+/// target.draw(&(&vertex_buffer, &instances, &index_buffer, &uniforms));
+/// ```
+///
+pub struct InstancesList<'a> {
+    buffers: Vec<(gl::types::GLuint, uint, VertexBindings)>,
+    count: uint,
+    marker: marker::ContravariantLifetime<'a>,
+}
+
+/// This public function is accessible from within `simple_gl` but not for the user.
+pub fn get_instances_clone(instances: &InstancesList)
+    -> (Vec<(gl::types::GLuint, uint, VertexBindings)>, uint)
+{
+    (instances.buffers.clone(), instances.count)
+}
+
+impl<'a> InstancesList<'a> {
+    /// Builds a list of `count` instances without per-instance attributes.
+    pub fn new(count: uint) -> InstancesList<'a> {
+        InstancesList {
+            buffers: Vec::new(),
+            count: count,
+            marker: marker::ContravariantLifetime,
+        }
+    }
+
+    /// Builds a list of instances whose attributes are in `buffer`. There is one instance
+    /// per element of the buffer.
+    pub fn from_buffer<T>(buffer: &'a VertexBuffer<T>) -> InstancesList<'a> {
+        InstancesList::new(buffer.elements_count).with_buffer(buffer)
+    }
+
+    /// Adds a buffer that contains other per-instance attributes.
+    ///
+    /// Fails if the buffer has less elements than the number of instances.
+    pub fn with_buffer<T>(mut self, buffer: &'a VertexBuffer<T>) -> InstancesList<'a> {
+        if buffer.elements_count < self.count {
+            fail!("The per-instance buffer contains {} elements but {} instances are drawn",
+                buffer.elements_count, self.count);
+        }
+
+        self.buffers.push(get_clone(buffer));
+        self
+    }
+
+    /// Returns the number of instances.
+    pub fn len(&self) -> uint {
+        self.count
+    }
+}
+
+/// For each binding, the data type, number of elements, offset, and whether the values
+/// must be normalized.
 /// Includes the total size.
 #[doc(hidden)]
-pub type VertexBindings = HashMap<String, (gl::types::GLenum, gl::types::GLint, uint)>;
+pub type VertexBindings = HashMap<String, (gl::types::GLenum, gl::types::GLint, uint, bool)>;
 
 /// Trait for structures that represent a vertex.
 #[doc(hidden)]
@@ -0,0 +1,1294 @@
+#![feature(phase)]
+
+#[phase(plugin)]
+extern crate simple_gl_macros;
+
+extern crate glutin;
+extern crate simple_gl;
+
+#[cfg(feature = "headless")]
+fn build_display() -> simple_gl::Display {
+    use simple_gl::DisplayBuild;
+
+    glutin::HeadlessRendererBuilder::new(64, 64).build_simple_gl().unwrap()
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn clear_color() {
+    let display = build_display();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 1.0, 0.0, 1.0);
+    target.finish();
+
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot.len(), 64);
+
+    for row in screenshot.iter() {
+        assert_eq!(row.len(), 64);
+        assert!(row.iter().all(|&pixel| pixel == (0, 255, 0, 255)));
+    }
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn screenshot_last_finished_frame() {
+    let display = build_display();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 1.0, 0.0, 1.0);
+    target.finish();
+
+    // the frame being drawn is not visible until it is finished
+    let mut target = display.draw();
+    target.clear_color(1.0, 0.0, 0.0, 1.0);
+    assert_eq!(display.screenshot()[32][32], (0, 255, 0, 255));
+    target.finish();
+
+    assert_eq!(display.screenshot()[32][32], (255, 0, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn draw_triangle() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    // a triangle that covers the bottom-left half of the framebuffer
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  1.0] },
+            Vertex { iPosition: [ 1.0, -1.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+    target.finish();
+
+    // the rows of the screenshot start with the top row
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot[60][4], (255, 0, 0, 255));
+    assert_eq!(screenshot[4][60], (0, 0, 0, 255));
+}
+
+/// Draws a triangle that covers the whole framebuffer with the given fragment shader, and
+/// returns the color of the pixel at the center.
+#[cfg(feature = "headless")]
+fn draw_fullscreen(display: &simple_gl::Display, fragment_shader: &str,
+                   set_uniforms: |&mut simple_gl::ProgramUniforms|) -> (u8, u8, u8, u8)
+{
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    let vertex_buffer = simple_gl::VertexBuffer::new(display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  3.0] },
+            Vertex { iPosition: [ 3.0, -1.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        fragment_shader, None).unwrap();
+
+    let mut uniforms = program.build_uniforms();
+    set_uniforms(&mut uniforms);
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 0.0);
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+    target.finish();
+
+    let screenshot = display.screenshot();
+    screenshot[32][32]
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn uniform_array_element_after_array() {
+    let display = build_display();
+
+    let pixel = draw_fullscreen(&display,
+        "
+            #version 110
+
+            uniform vec4 colors[2];
+
+            void main() {
+                gl_FragColor = colors[0] + colors[1];
+            }
+        ",
+        |uniforms| {
+            uniforms.set_array("colors", &[[1.0, 0.0, 0.0, 0.0f32], [0.0, 1.0, 0.0, 1.0]]);
+            uniforms.set_value("colors[0]", [0.0, 0.0, 1.0, 0.0f32]);
+        });
+
+    // writing the first element must not discard the second one
+    assert_eq!(pixel, (0, 255, 255, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn uniform_array_after_array_element() {
+    let display = build_display();
+
+    let pixel = draw_fullscreen(&display,
+        "
+            #version 110
+
+            uniform vec4 colors[2];
+
+            void main() {
+                gl_FragColor = colors[0] + colors[1];
+            }
+        ",
+        |uniforms| {
+            uniforms.set_value("colors[1]", [1.0, 0.0, 0.0, 0.0f32]);
+            uniforms.set_array("colors", &[[0.0, 0.0, 1.0, 1.0f32], [0.0, 1.0, 0.0, 0.0]]);
+        });
+
+    // the whole array overwrites the element that was set before
+    assert_eq!(pixel, (0, 255, 255, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn uniform_bool_and_matrix() {
+    let display = build_display();
+
+    let pixel = draw_fullscreen(&display,
+        "
+            #version 110
+
+            uniform bvec2 flags;
+            uniform mat2 matrix;
+
+            void main() {
+                vec2 color = matrix * vec2(1.0, 0.0);
+                gl_FragColor = vec4(color, flags.x ? 1.0 : 0.0, flags.y ? 1.0 : 0.0);
+            }
+        ",
+        |uniforms| {
+            uniforms.set_value("flags", [true, true]);
+            // the first column of the matrix
+            uniforms.set_value("matrix", [[0.0, 1.0], [1.0, 0.0f32]]);
+        });
+
+    assert_eq!(pixel, (0, 255, 255, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn vertex_buffer_mapping() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        value: [f32, ..2],
+    }
+
+    let mut vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![ Vertex { value: [5.0, 5.0] }, Vertex { value: [5.0, 5.0] } ]);
+
+    {
+        let mut mapping = vertex_buffer.map();
+
+        // the previous content is not kept
+        assert_eq!(mapping.as_slice()[1].value[0], 0.0);
+
+        mapping.as_mut_slice()[1].value = [1.0, 2.0];
+        mapping.finish().unwrap();
+    }
+
+    let data = vertex_buffer.read();
+    assert_eq!(data[0].value[0], 0.0);
+    assert_eq!(data[1].value[0], 1.0);
+    assert_eq!(data[1].value[1], 2.0);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn matrix_attribute() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+        #[allow(dead_code)]
+        iColor: [[f32, ..2], ..2],
+    }
+
+    let color = [[1.0, 0.0], [0.0, 1.0]];
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0], iColor: color },
+            Vertex { iPosition: [-1.0,  3.0], iColor: color },
+            Vertex { iPosition: [ 3.0, -1.0], iColor: color },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+            attribute mat2 iColor;
+            varying vec4 vColor;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+                vColor = vec4(iColor[0], iColor[1]);
+            }
+        ",
+        "
+            #version 110
+
+            varying vec4 vColor;
+
+            void main() {
+                gl_FragColor = vColor;
+            }
+        ",
+        None).unwrap();
+
+    assert!(program.check_vertex_buffers(&vertex_buffer, None).is_ok());
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 0.0);
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+    target.finish();
+
+    assert_eq!(display.screenshot()[32][32], (255, 0, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn attribute_type_mismatch() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![ Vertex { iPosition: [0.0, 0.0] } ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute mat2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition[0], iPosition[1]);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(1.0, 1.0, 1.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    // the columns of a matrix must all be provided
+    match program.check_vertex_buffers(&vertex_buffer, None) {
+        Err(simple_gl::AttributeTypeMismatch(ref name, _, _)) =>
+            assert_eq!(name.as_slice(), "iPosition"),
+        other => fail!("unexpected result: {}", other)
+    }
+}
+
+#[test]
+fn draw_parameters_defaults() {
+    let parameters: simple_gl::DrawParameters = std::default::Default::default();
+
+    // the rasterization state is always set, so that it doesn't leak from one draw to the next
+    assert_eq!(parameters.line_width, 1.0);
+    assert_eq!(parameters.point_size, Some(1.0));
+    assert!(parameters.polygon_mode == simple_gl::Fill);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn headless_depth_buffer() {
+    use simple_gl::DisplayBuild;
+
+    let display = glutin::HeadlessRendererBuilder::new(64, 64)
+        .build_simple_gl_with_depth_stencil(24, 8).unwrap();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..3],
+        #[allow(dead_code)]
+        iColor: [f32, ..3],
+    }
+
+    // a red triangle in front of a green triangle, both covering the whole framebuffer
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0, 0.0], iColor: [1.0, 0.0, 0.0] },
+            Vertex { iPosition: [-1.0,  3.0, 0.0], iColor: [1.0, 0.0, 0.0] },
+            Vertex { iPosition: [ 3.0, -1.0, 0.0], iColor: [1.0, 0.0, 0.0] },
+            Vertex { iPosition: [-1.0, -1.0, 0.5], iColor: [0.0, 1.0, 0.0] },
+            Vertex { iPosition: [-1.0,  3.0, 0.5], iColor: [0.0, 1.0, 0.0] },
+            Vertex { iPosition: [ 3.0, -1.0, 0.5], iColor: [0.0, 1.0, 0.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2, 3, 4, 5 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec3 iPosition;
+            attribute vec3 iColor;
+            varying vec3 vColor;
+
+            void main() {
+                gl_Position = vec4(iPosition, 1.0);
+                vColor = iColor;
+            }
+        ",
+        "
+            #version 110
+
+            varying vec3 vColor;
+
+            void main() {
+                gl_FragColor = vec4(vColor, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let parameters = simple_gl::DrawParameters {
+        depth_function: simple_gl::IfLess,
+        depth_write: true,
+        .. std::default::Default::default()
+    };
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.clear_depth(1.0);
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms, &parameters));
+    target.finish();
+
+    assert_eq!(display.screenshot()[32][32], (255, 0, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn headless_multisampling_not_supported() {
+    use simple_gl::DisplayBuild;
+
+    match glutin::HeadlessRendererBuilder::new(64, 64).build_simple_gl_with_multisampling(4) {
+        Err(simple_gl::MultisamplingNotSupported(4)) => (),
+        Err(err) => fail!("unexpected error: {}", err),
+        Ok(_) => fail!("a headless display can't be multisampled")
+    }
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn framebuffer_creation_errors() {
+    let display = build_display();
+
+    let texture = simple_gl::Texture::new(&display,
+        Vec::from_elem(64 * 64, (0u8, 0u8, 0u8, 0u8)).as_slice(), 64, 64, 1, 1);
+    let small = simple_gl::Texture::new(&display,
+        Vec::from_elem(32 * 32, (0u8, 0u8, 0u8, 0u8)).as_slice(), 32, 32, 1, 1);
+
+    match simple_gl::FramebufferBuilder::new(&display).draw() {
+        Err(simple_gl::FramebufferNoColorAttachment) => (),
+        Err(err) => fail!("unexpected error: {}", err),
+        Ok(_) => fail!("a framebuffer without any color texture can't be drawn on")
+    }
+
+    match simple_gl::FramebufferBuilder::new(&display).with_color_texture(&texture)
+        .with_color_texture(&small).build()
+    {
+        Err(simple_gl::FramebufferDimensionsMismatch) => (),
+        Err(err) => fail!("unexpected error: {}", err),
+        Ok(_) => fail!("attachments of different dimensions can't be combined")
+    }
+
+    match simple_gl::FramebufferBuilder::new(&display).with_color_texture(&texture)
+        .with_depth_stencil_attachment(simple_gl::DepthTextureAttachment(&texture)).draw()
+    {
+        Err(simple_gl::FramebufferNotADepthTexture) => (),
+        Err(err) => fail!("unexpected error: {}", err),
+        Ok(_) => fail!("a color texture can't be attached as a depth buffer")
+    }
+
+    let mut target = simple_gl::FramebufferBuilder::new(&display).with_color_texture(&texture)
+        .draw().unwrap();
+    target.clear_color(1.0, 0.0, 0.0, 1.0);
+    target.finish();
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn texture_read() {
+    let display = build_display();
+
+    let data = vec![
+        (255u8, 0u8, 0u8, 255u8), (0, 255, 0, 255),
+        (0, 0, 255, 255), (255, 255, 255, 0),
+    ];
+
+    let texture = simple_gl::Texture::new(&display, data.as_slice(), 2, 2, 1, 1);
+
+    // the texels are returned in the order in which they were uploaded
+    let texels: Vec<(u8, u8, u8, u8)> = texture.read();
+    assert_eq!(texels, data);
+
+    let texels: Vec<(u8, u8, u8, u8)> = texture.read_mipmap(1, 0);
+    assert_eq!(texels.len(), 1);
+}
+
+#[cfg(feature = "headless")]
+static UNIFORM_ERRORS_FRAGMENT_SHADER: &'static str = "
+    #version 110
+
+    uniform vec4 color;
+    uniform float weights[2];
+
+    void main() {
+        gl_FragColor = color * (weights[0] + weights[1]);
+    }
+";
+
+#[test]
+#[cfg(feature = "headless")]
+fn uniform_errors() {
+    let display = build_display();
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            void main() {
+                gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+            }
+        ",
+        UNIFORM_ERRORS_FRAGMENT_SHADER, None).unwrap();
+
+    let mut uniforms = program.build_uniforms();
+
+    assert_eq!(uniforms.try_set_value("color", [1.0, 0.0, 0.0, 1.0f32]), Ok(()));
+    assert_eq!(uniforms.try_set_array("weights", &[0.5f32, 0.5]), Ok(()));
+
+    assert_eq!(uniforms.try_set_value("missing", 1.0f32),
+        Err(simple_gl::UniformNotFound("missing".to_string())));
+
+    assert_eq!(uniforms.try_set_value("color", 1.0f32),
+        Err(simple_gl::UniformTypeMismatch("color".to_string(), "vec4".to_string(),
+            "float".to_string())));
+
+    assert_eq!(uniforms.try_set_array("weights", &[1.0f32, 1.0, 1.0]),
+        Err(simple_gl::UniformArrayTooSmall("weights".to_string(), 2, 3)));
+
+    // unknown uniforms are ignored when strict mode is disabled
+    uniforms.set_value("missing", 1.0f32);
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn uniform_not_found_strict() {
+    let display = build_display();
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            void main() {
+                gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+            }
+        ",
+        UNIFORM_ERRORS_FRAGMENT_SHADER, None).unwrap();
+
+    let mut uniforms = program.build_uniforms();
+    uniforms.set_strict(true);
+    uniforms.set_value("missing", 1.0f32);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn sampler_wrap_function() {
+    let display = build_display();
+
+    // red on the left column, green on the right column
+    let texture = simple_gl::Texture::new(&display, &[
+        (255u8, 0u8, 0u8, 255u8), (0, 255, 0, 255),
+        (255, 0, 0, 255), (0, 255, 0, 255),
+    ], 2, 2, 1, 1);
+
+    let fragment_shader = "
+        #version 110
+
+        uniform sampler2D tex;
+
+        void main() {
+            gl_FragColor = texture2D(tex, vec2(1.25, 0.5));
+        }
+    ";
+
+    let behavior = |wrap| simple_gl::SamplerBehavior {
+        wrap_function: (wrap, wrap, wrap),
+        minify_filter: simple_gl::Nearest,
+        magnify_filter: simple_gl::Nearest,
+        mipmap_filter: None,
+        .. std::default::Default::default()
+    };
+
+    let pixel = draw_fullscreen(&display, fragment_shader, |uniforms| {
+        uniforms.set_texture_with_sampler("tex", &texture, &behavior(simple_gl::Repeat));
+    });
+    assert_eq!(pixel, (255, 0, 0, 255));
+
+    let pixel = draw_fullscreen(&display, fragment_shader, |uniforms| {
+        uniforms.set_texture_with_sampler("tex", &texture, &behavior(simple_gl::Clamp));
+    });
+    assert_eq!(pixel, (0, 255, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn texture_float_format() {
+    let display = build_display();
+
+    // values outside of 0.0 .. 1.0 are kept by floating-point formats
+    let data = vec![2.5f32, -1.0, 0.25, 1000.0];
+    let texture = simple_gl::Texture::new_with_format(&display, simple_gl::R32F,
+        data.as_slice(), 2, 2, 1, 1).unwrap();
+
+    let texels: Vec<f32> = texture.read();
+    assert_eq!(texels, data);
+
+    match simple_gl::Texture::new_with_format(&display, simple_gl::RGBA8UI,
+        &[(0.0f32, 0.0f32, 0.0f32, 0.0f32)], 1, 1, 1, 1)
+    {
+        Err(simple_gl::IncompatibleDataType(simple_gl::RGBA8UI, 4)) => (),
+        Err(err) => fail!("unexpected error: {}", err),
+        Ok(_) => fail!("an integer texture can't be built from floats")
+    }
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn cubemap_faces() {
+    let display = build_display();
+
+    let colors = [
+        (255u8, 0u8, 0u8, 255u8), (0, 255, 0, 255), (0, 0, 255, 255),
+        (255, 255, 0, 255), (0, 255, 255, 255), (255, 0, 255, 255),
+    ];
+
+    let mut cubemap = simple_gl::Texture::new_cubemap(&display, [
+        colors.slice(0, 1), colors.slice(1, 2), colors.slice(2, 3),
+        colors.slice(3, 4), colors.slice(4, 5), colors.slice(5, 6),
+    ], 1);
+
+    let faces = [
+        simple_gl::PositiveX, simple_gl::NegativeX, simple_gl::PositiveY,
+        simple_gl::NegativeY, simple_gl::PositiveZ, simple_gl::NegativeZ,
+    ];
+
+    for (&face, &color) in faces.iter().zip(colors.iter()) {
+        let texels: Vec<(u8, u8, u8, u8)> = cubemap.read_face(face);
+        assert_eq!(texels, vec![color]);
+    }
+
+    // the direction of the sample determines the face
+    let pixel = draw_fullscreen(&display,
+        "
+            #version 110
+
+            uniform samplerCube cubemap;
+
+            void main() {
+                gl_FragColor = textureCube(cubemap, vec3(0.0, -1.0, 0.0));
+            }
+        ",
+        |uniforms| uniforms.set_texture("cubemap", &cubemap));
+    assert_eq!(pixel, colors[3]);
+
+    // drawing on a face doesn't modify the other ones
+    {
+        let mut target = cubemap.draw_face(simple_gl::PositiveY);
+        target.clear_color(1.0, 1.0, 1.0, 1.0);
+        target.finish();
+    }
+
+    let texels: Vec<(u8, u8, u8, u8)> = cubemap.read_face(simple_gl::PositiveY);
+    assert_eq!(texels, vec![(255, 255, 255, 255)]);
+    let texels: Vec<(u8, u8, u8, u8)> = cubemap.read_face(simple_gl::PositiveX);
+    assert_eq!(texels, vec![colors[0]]);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn texture_write() {
+    let display = build_display();
+
+    let black = (0u8, 0u8, 0u8, 255u8);
+    let red = (255u8, 0u8, 0u8, 255u8);
+
+    let mut texture = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, black).as_slice(), 4, 4, 1, 1);
+
+    texture.write((1, 2, 0), (2, 1, 1), &[red, red]);
+
+    let texels: Vec<(u8, u8, u8, u8)> = texture.read();
+    for y in range(0u, 4) {
+        for x in range(0u, 4) {
+            let expected = if y == 2 && (x == 1 || x == 2) { red } else { black };
+            assert_eq!(texels[y * 4 + x], expected);
+        }
+    }
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn texture_write_out_of_bounds() {
+    let display = build_display();
+
+    let mut texture = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, (0u8, 0u8, 0u8, 0u8)).as_slice(), 4, 4, 1, 1);
+
+    texture.write((3, 0, 0), (2, 1, 1), &[(0u8, 0u8, 0u8, 0u8), (0, 0, 0, 0)]);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn buffers_read() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    let mut vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  1.0] },
+            Vertex { iPosition: [ 1.0, -1.0] },
+        ]
+    );
+
+    vertex_buffer.write(1, &[Vertex { iPosition: [0.5, 0.25] }]);
+
+    let vertices = vertex_buffer.read();
+    assert_eq!(vertices.len(), 3);
+    assert_eq!(vertices[0].iPosition, [-1.0, -1.0]);
+    assert_eq!(vertices[1].iPosition, [0.5, 0.25]);
+    assert_eq!(vertices[2].iPosition, [1.0, -1.0]);
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2, 2, 1, 0 ]);
+
+    let indices: Vec<u16> = index_buffer.read();
+    assert_eq!(indices, vec![0, 1, 2, 2, 1, 0]);
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn index_buffer_read_wrong_type() {
+    let display = build_display();
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let _: Vec<u8> = index_buffer.read();
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn instanced_draw() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    #[vertex_format]
+    struct Instance {
+        #[allow(dead_code)]
+        iOffset: [f32, ..2],
+    }
+
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-0.25, -0.25] },
+            Vertex { iPosition: [-0.25,  0.25] },
+            Vertex { iPosition: [ 0.25, -0.25] },
+        ]
+    );
+
+    // one triangle on the left of the framebuffer and one on the right
+    let per_instance = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Instance { iOffset: [-0.5, 0.0] },
+            Instance { iOffset: [ 0.5, 0.0] },
+        ]
+    );
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+            attribute vec2 iOffset;
+
+            void main() {
+                gl_Position = vec4(iPosition + iOffset, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.draw(&(&vertex_buffer, &simple_gl::InstancesList::from_buffer(&per_instance),
+        &simple_gl::NoIndices(simple_gl::TrianglesList), &uniforms));
+    target.finish();
+
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot[35][12], (255, 0, 0, 255));
+    assert_eq!(screenshot[35][32], (0, 0, 0, 255));
+    assert_eq!(screenshot[35][44], (255, 0, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn index_buffer_slice() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    // a triangle in the bottom-left half of the framebuffer, then one in the top-right half
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  1.0] },
+            Vertex { iPosition: [ 1.0, -1.0] },
+            Vertex { iPosition: [ 1.0,  1.0] },
+            Vertex { iPosition: [ 1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  1.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2, 0, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.draw(&(&vertex_buffer, &index_buffer.slice(3, 3).with_base_vertex(3), &uniforms));
+    target.finish();
+
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot[4][60], (255, 0, 0, 255));
+    assert_eq!(screenshot[60][4], (0, 0, 0, 255));
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn index_buffer_slice_out_of_bounds() {
+    let display = build_display();
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2, 0, 1, 2 ]);
+
+    index_buffer.slice(4, 3);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn vertex_array_objects_reuse() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+        #[allow(dead_code)]
+        iColor: [f32, ..3],
+    }
+
+    let build_vertex_buffer = |color: [f32, ..3]| {
+        simple_gl::VertexBuffer::new(&display,
+            vec![
+                Vertex { iPosition: [-1.0, -1.0], iColor: color },
+                Vertex { iPosition: [-1.0,  3.0], iColor: color },
+                Vertex { iPosition: [ 3.0, -1.0], iColor: color },
+            ]
+        )
+    };
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let colored = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec3 iColor;
+            attribute vec2 iPosition;
+
+            varying vec3 vColor;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+                vColor = iColor;
+            }
+        ",
+        "
+            #version 110
+
+            varying vec3 vColor;
+
+            void main() {
+                gl_FragColor = vec4(vColor, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let blue = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(0.0, 0.0, 1.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let colored = colored.build_uniforms();
+    let blue = blue.build_uniforms();
+
+    let draw = |vertex_buffer: &simple_gl::VertexBuffer<Vertex>,
+                uniforms: &simple_gl::ProgramUniforms| {
+        let mut target = display.draw();
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+        target.draw(&(vertex_buffer, &index_buffer, uniforms));
+        target.finish();
+        display.screenshot()[32][32]
+    };
+
+    // the same buffers are drawn with programs whose attributes have different locations
+    let vertex_buffer = build_vertex_buffer([0.0, 1.0, 0.0]);
+    assert_eq!(draw(&vertex_buffer, &colored), (0, 255, 0, 255));
+    assert_eq!(draw(&vertex_buffer, &blue), (0, 0, 255, 255));
+    assert_eq!(draw(&vertex_buffer, &colored), (0, 255, 0, 255));
+
+    // a new buffer can reuse the identifier of a destroyed one
+    drop(vertex_buffer);
+    let vertex_buffer = build_vertex_buffer([1.0, 0.0, 0.0]);
+    assert_eq!(draw(&vertex_buffer, &colored), (255, 0, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn integer_attribute() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+        #[allow(dead_code)]
+        iValue: [i32, ..2],
+    }
+
+    // 16777217 can't be represented by a float, so it is only found back if the values are
+    //  passed as integers
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0], iValue: [16777217, -3] },
+            Vertex { iPosition: [-1.0,  3.0], iValue: [16777217, -3] },
+            Vertex { iPosition: [ 3.0, -1.0], iValue: [16777217, -3] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 130
+
+            in vec2 iPosition;
+            in ivec2 iValue;
+
+            out vec3 vColor;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+                vColor = vec3(iValue.x == 16777217 ? 1.0 : 0.0, iValue.y == -3 ? 1.0 : 0.0, 0.0);
+            }
+        ",
+        "
+            #version 130
+
+            in vec3 vColor;
+
+            void main() {
+                gl_FragColor = vec4(vColor, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+    target.finish();
+
+    assert_eq!(display.screenshot()[32][32], (255, 255, 0, 255));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn multiple_render_targets() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  3.0] },
+            Vertex { iPosition: [ 3.0, -1.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 130
+
+            in vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 130
+
+            out vec4 oGreen;
+            out vec4 oRed;
+
+            void main() {
+                oRed = vec4(1.0, 0.0, 0.0, 1.0);
+                oGreen = vec4(0.0, 1.0, 0.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let red = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, (0u8, 0u8, 0u8, 0u8)).as_slice(), 4, 4, 1, 1);
+    let green = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, (0u8, 0u8, 0u8, 0u8)).as_slice(), 4, 4, 1, 1);
+
+    {
+        // the textures are attached in a different order than the outputs are declared
+        let mut target = simple_gl::FramebufferBuilder::new(&display)
+            .with_named_color_texture("oRed", &red)
+            .with_named_color_texture("oGreen", &green)
+            .draw().unwrap();
+
+        target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+        target.finish();
+    }
+
+    let texels: Vec<(u8, u8, u8, u8)> = red.read();
+    assert!(texels.iter().all(|&texel| texel == (255, 0, 0, 255)));
+
+    let texels: Vec<(u8, u8, u8, u8)> = green.read();
+    assert!(texels.iter().all(|&texel| texel == (0, 255, 0, 255)));
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn multisample_resolve() {
+    let display = build_display();
+
+    let mut texture = simple_gl::Texture::new_multisample(&display, simple_gl::RGBA8,
+        64, 64, 4).unwrap();
+
+    let mut target = texture.draw();
+    target.clear_color(1.0, 0.0, 0.0, 1.0);
+
+    let mut destination = display.draw();
+    destination.clear_color(0.0, 0.0, 0.0, 1.0);
+    target.resolve(&mut destination);
+    destination.finish();
+    target.finish();
+
+    let screenshot = display.screenshot();
+    assert!(screenshot.iter().all(|row| row.iter().all(|&pixel| pixel == (255, 0, 0, 255))));
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn multisample_resolve_dimensions_mismatch() {
+    let display = build_display();
+
+    let mut texture = simple_gl::Texture::new_multisample(&display, simple_gl::RGBA8,
+        32, 32, 4).unwrap();
+
+    texture.draw().resolve(&mut display.draw());
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn blit_stretched() {
+    let display = build_display();
+
+    let red = (255u8, 0u8, 0u8, 255u8);
+    let green = (0u8, 255u8, 0u8, 255u8);
+    let blue = (0u8, 0u8, 255u8, 255u8);
+    let white = (255u8, 255u8, 255u8, 255u8);
+
+    // the first row of texels is the bottom row
+    let mut texture = simple_gl::Texture::new(&display, &[red, green, blue, white], 2, 2, 1, 1);
+
+    let source = texture.draw();
+    let mut destination = display.draw();
+    destination.clear_color(0.0, 0.0, 0.0, 1.0);
+
+    // each texel covers a quarter of the left half of the framebuffer
+    source.blit(&simple_gl::Rect { left: 0, bottom: 0, width: 2, height: 2 },
+        &mut destination,
+        &simple_gl::Rect { left: 0, bottom: 0, width: 32, height: 64 },
+        &simple_gl::BlitMask { color: true, depth: false, stencil: false },
+        simple_gl::Nearest);
+
+    destination.finish();
+    source.finish();
+
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot[60][4], red);
+    assert_eq!(screenshot[60][20], green);
+    assert_eq!(screenshot[4][4], blue);
+    assert_eq!(screenshot[4][20], white);
+    assert_eq!(screenshot[32][48], (0, 0, 0, 255));
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn blit_source_out_of_bounds() {
+    let display = build_display();
+
+    let mut texture = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, (0u8, 0u8, 0u8, 0u8)).as_slice(), 4, 4, 1, 1);
+
+    texture.draw().blit(&simple_gl::Rect { left: 2, bottom: 0, width: 4, height: 4 },
+        &mut display.draw(),
+        &simple_gl::Rect { left: 0, bottom: 0, width: 4, height: 4 },
+        &simple_gl::BlitMask { color: true, depth: false, stencil: false },
+        simple_gl::Nearest);
+}
+
+#[test]
+#[should_fail]
+#[cfg(feature = "headless")]
+fn blit_destination_out_of_bounds() {
+    let display = build_display();
+
+    let mut texture = simple_gl::Texture::new(&display,
+        Vec::from_elem(4 * 4, (0u8, 0u8, 0u8, 0u8)).as_slice(), 4, 4, 1, 1);
+
+    texture.draw().blit(&simple_gl::Rect { left: 0, bottom: 0, width: 4, height: 4 },
+        &mut display.draw(),
+        &simple_gl::Rect { left: 62, bottom: 0, width: 4, height: 4 },
+        &simple_gl::BlitMask { color: true, depth: false, stencil: false },
+        simple_gl::Nearest);
+}
+
+#[test]
+#[cfg(feature = "headless")]
+fn scissor_and_viewport() {
+    let display = build_display();
+
+    #[vertex_format]
+    struct Vertex {
+        #[allow(dead_code)]
+        iPosition: [f32, ..2],
+    }
+
+    let vertex_buffer = simple_gl::VertexBuffer::new(&display,
+        vec![
+            Vertex { iPosition: [-1.0, -1.0] },
+            Vertex { iPosition: [-1.0,  3.0] },
+            Vertex { iPosition: [ 3.0, -1.0] },
+        ]
+    );
+
+    let index_buffer = simple_gl::IndexBuffer::new(&display, simple_gl::TrianglesList,
+        &[ 0u16, 1, 2 ]);
+
+    let program = simple_gl::Program::new(&display,
+        "
+            #version 110
+
+            attribute vec2 iPosition;
+
+            void main() {
+                gl_Position = vec4(iPosition, 0.0, 1.0);
+            }
+        ",
+        "
+            #version 110
+
+            void main() {
+                gl_FragColor = vec4(0.0, 1.0, 0.0, 1.0);
+            }
+        ",
+        None).unwrap();
+
+    let uniforms = program.build_uniforms();
+
+    let mut target = display.draw();
+    target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+    // only the bottom-left quarter is cleared
+    target.set_scissor(Some(simple_gl::Rect { left: 0, bottom: 0, width: 32, height: 32 }));
+    target.clear_color(1.0, 0.0, 0.0, 1.0);
+    target.set_scissor(None);
+
+    // the triangle covers the whole viewport, which is the right half
+    target.set_viewport(Some(simple_gl::Rect { left: 32, bottom: 0, width: 32, height: 64 }));
+    target.draw(&(&vertex_buffer, &index_buffer, &uniforms));
+    target.finish();
+
+    let screenshot = display.screenshot();
+    assert_eq!(screenshot[60][4], (255, 0, 0, 255));
+    assert_eq!(screenshot[4][4], (0, 0, 0, 255));
+    assert_eq!(screenshot[4][60], (0, 255, 0, 255));
+    assert_eq!(screenshot[60][60], (0, 255, 0, 255));
+}